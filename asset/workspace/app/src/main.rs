@@ -0,0 +1,3 @@
+fn main() {
+    println!("{}", helper::add(1, 2));
+}
@@ -0,0 +1,8 @@
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
@@ -7,8 +7,9 @@ extern crate rustc_span;
 use rustc_ast::ast::{Item, VisibilityKind};
 use rustc_ast::visit::{walk_crate, walk_item, Visitor};
 use rustc_span::edition::Edition;
-use rustc_tools::with_ast_parser;
+use rustc_tools::{with_ast_parser, DiagnosticOutput};
 
+use std::convert::Infallible;
 use std::path::Path;
 
 struct PublicItemVisitor;
@@ -34,13 +35,19 @@ fn main() {
     };
     let p = Path::new(&p);
     println!("Running AST example on `{}`", p.display());
-    with_ast_parser(p, Edition::Edition2018, |sess, krate| {
-        println!(
-            "Listing all public items of crate (edition: {:?})",
-            sess.edition
-        );
-        // We start the visitor run by calling `walk_crate`.
-        walk_crate(&mut PublicItemVisitor, krate);
-    })
+    with_ast_parser(
+        p,
+        Edition::Edition2018,
+        DiagnosticOutput::Stderr,
+        |sess, krate| {
+            println!(
+                "Listing all public items of crate (edition: {:?})",
+                sess.edition
+            );
+            // We start the visitor run by calling `walk_crate`.
+            walk_crate(&mut PublicItemVisitor, krate);
+            Ok::<_, Infallible>(())
+        },
+    )
     .unwrap();
 }
@@ -0,0 +1,45 @@
+//! This example shows how to run an analysis on all the crates of a cargo workspace without
+//! having to provide the rustc arguments yourself.
+//!
+//! This binary is run twice: first by you, then by cargo (once for each workspace crate).
+
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+// We need to import them like this otherwise it doesn't work.
+extern crate rustc_hir;
+extern crate rustc_middle;
+
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_middle::ty::TyCtxt;
+use rustc_tools::{CargoDriver, Error};
+
+use std::convert::Infallible;
+use std::process::ExitCode;
+
+fn count_items(tcx: TyCtxt<'_>) -> Result<(), Infallible> {
+    let nb_items = tcx.hir().items().count();
+    println!(
+        "=> crate `{}` has {} items",
+        tcx.crate_name(LOCAL_CRATE),
+        nb_items
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    // When this binary is run by cargo, the arguments are the ones for rustc so this value is
+    // meaningless. It doesn't matter though because the `CargoDriver` ignores it in this case.
+    let manifest_path = std::env::args().nth(1).unwrap_or_default();
+    if !CargoDriver::is_wrapper() {
+        println!("Running cargo example on `{}`", manifest_path);
+    }
+    match CargoDriver::new(manifest_path).with_tyctxt(count_items) {
+        Ok(()) => ExitCode::SUCCESS,
+        // The compilation errors have already been displayed by rustc.
+        Err(Error::Compilation(_)) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,59 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+// We need to import them like this otherwise it doesn't work.
+extern crate rustc_ast;
+extern crate rustc_span;
+
+use rustc_ast::ast::Expr;
+use rustc_ast::visit::{walk_crate, walk_expr, Visitor};
+use rustc_span::source_map::SourceMap;
+use rustc_tools::{macro_call_site, with_expanded_ast, AnalysisConfig};
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+struct MacroCallsVisitor<'a> {
+    source_map: &'a SourceMap,
+    // Number of expressions generated by each macro call.
+    calls: BTreeMap<String, usize>,
+}
+
+// We only implement the methods we want to overload, so just `visit_expr`.
+impl<'a, 'ast> Visitor<'ast> for MacroCallsVisitor<'a> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        if let Some(call) = macro_call_site(e.span) {
+            let location = self.source_map.span_to_diagnostic_string(call.span);
+            *self
+                .calls
+                .entry(format!("{}! at {}", call.name, location))
+                .or_default() += 1;
+        }
+        // If we don't call this function, it'll never go through the children of the expression!
+        walk_expr(self, e);
+    }
+}
+
+fn main() {
+    let p = match std::env::args().nth(1) {
+        Some(p) => p,
+        None => {
+            eprintln!("Missing file operand");
+            return;
+        }
+    };
+    println!("Running expanded AST example on `{}`", p);
+    with_expanded_ast(&AnalysisConfig::new(p), |tcx, krate, _resolver| {
+        println!("Listing all macro calls with the number of expressions they generated");
+        let mut visitor = MacroCallsVisitor {
+            source_map: tcx.sess.source_map(),
+            calls: BTreeMap::new(),
+        };
+        // We start the visitor run by calling `walk_crate`.
+        walk_crate(&mut visitor, krate);
+        for (call, count) in visitor.calls {
+            println!("=> {}: {} expressions", call, count);
+        }
+        Ok::<_, Infallible>(())
+    })
+    .unwrap();
+}
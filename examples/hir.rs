@@ -10,7 +10,9 @@ use rustc_hir::intravisit::{walk_item, Visitor};
 use rustc_hir::Item;
 use rustc_middle::ty::TyCtxt;
 use rustc_span::{FileName, Span};
-use rustc_tools::with_tyctxt;
+use rustc_tools::{with_tyctxt, DiagnosticOutput};
+
+use std::convert::Infallible;
 
 struct ItemsLocator<'tcx> {
     tcx: TyCtxt<'tcx>,
@@ -61,7 +63,7 @@ fn main() {
         return;
     }
     println!("Running HIR example with arguments `{:?}`", args);
-    with_tyctxt(&args, |tcx| {
+    with_tyctxt(&args, DiagnosticOutput::Stderr, |tcx| {
         println!("Here are the available crates:");
         for krate in tcx.crates(()).iter() {
             println!("  * {}", tcx.crate_name(*krate));
@@ -73,6 +75,7 @@ fn main() {
         // We start the visitor run by calling `visit_all_item_likes_in_crate`.
         tcx.hir()
             .visit_all_item_likes_in_crate(&mut ItemsLocator { tcx });
+        Ok::<_, Infallible>(())
     })
     .unwrap();
 }
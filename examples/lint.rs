@@ -30,7 +30,7 @@ use rustc_hir::{Body, FnDecl};
 use rustc_lint::{EarlyContext, EarlyLintPass, LateContext, LateLintPass, LintContext, LintStore};
 use rustc_session::{declare_lint_pass, declare_tool_lint};
 use rustc_span::Span;
-use rustc_tools::with_lints;
+use rustc_tools::{with_lints, DiagnosticOutput};
 
 declare_tool_lint! {
     // `lint` is the name of the binary here. It's required when creating a lint.
@@ -104,10 +104,15 @@ fn main() -> Result<(), ()> {
         return Err(());
     }
     println!("Running lint example with arguments `{:?}`", args);
-    with_lints(&args, vec![], |store: &mut LintStore| {
-        store.register_early_pass(|| Box::new(WarnGenerics));
-        store.register_late_pass(|_| Box::new(OddFunctionLineCount));
-    })
+    with_lints(
+        &args,
+        vec![],
+        DiagnosticOutput::Stderr,
+        |store: &mut LintStore| {
+            store.register_early_pass(|| Box::new(WarnGenerics));
+            store.register_late_pass(|_| Box::new(OddFunctionLineCount));
+        },
+    )
     .map(|_| ())
     .map_err(|_| ())
 }
@@ -0,0 +1,75 @@
+//! This example shows how to register lints with a `LintRegistry` instead of calling the
+//! `LintStore` methods yourself (take a look at `examples/lint.rs` first). The registry also keeps
+//! the documentation of the lints, which is printed when the `--list-lints` option is used:
+//!
+//! ```bash
+//! $ cargo run --example lint_registry -- --list-lints
+//! $ cargo run --example lint_registry -- --list-lints=markdown
+//! $ cargo run --example lint_registry -- --list-lints=json
+//! ```
+
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+// We need to import them like this otherwise it doesn't work.
+extern crate rustc_ast;
+extern crate rustc_lint;
+extern crate rustc_session;
+
+use rustc_lint::{EarlyContext, EarlyLintPass, LintContext};
+use rustc_session::{declare_lint_pass, declare_tool_lint};
+use rustc_tools::{with_lints, CatalogFormat, DiagnosticOutput, LintInfo, LintRegistry};
+
+declare_tool_lint! {
+    // `lint` is the name of the binary here. It's required when creating a lint.
+    pub lint::WARN_GENERICS,
+    Warn,
+    "warns if any item has generics",
+    report_in_external_macro: false
+}
+declare_lint_pass!(WarnGenerics => [WARN_GENERICS]);
+
+impl EarlyLintPass for WarnGenerics {
+    fn check_item(&mut self, cx: &EarlyContext<'_>, item: &rustc_ast::Item) {
+        if let Some(generics) = item.kind.generics() {
+            if generics.params.is_empty() {
+                return;
+            }
+            cx.struct_span_lint(WARN_GENERICS, generics.span, "generics are ugly", |diag| {
+                diag
+            });
+        }
+    }
+}
+
+fn main() -> Result<(), ()> {
+    // The lints are in the `lint::style` and `lint::all` groups, so `#[allow(lint::style)]` can
+    // be used in the analyzed code.
+    let registry = LintRegistry::new()
+        .tool("lint")
+        .lint(
+            LintInfo::new(WARN_GENERICS, "style")
+                .explanation("Generics make the code harder to read, so they're not allowed.")
+                .example("fn foo<T>(t: T) {}"),
+        )
+        .early_pass(|| Box::new(WarnGenerics));
+
+    let mut args: Vec<String> = std::env::args().collect();
+    if let Some(format) = args.iter().find_map(|arg| arg.strip_prefix("--list-lints")) {
+        let format = match format {
+            "=markdown" => CatalogFormat::Markdown,
+            "=json" => CatalogFormat::Json,
+            _ => CatalogFormat::Text,
+        };
+        println!("{}", registry.catalog(format));
+        return Ok(());
+    }
+    println!("Running lint_registry example with arguments `{:?}`", args);
+    // Only the diagnostics matter: the metadata of the crate is written in the temporary
+    // directory instead of compiling it into the current one.
+    args.push("--emit=metadata".to_owned());
+    args.push(format!("--out-dir={}", std::env::temp_dir().display()));
+    with_lints(&args, vec![], DiagnosticOutput::Stderr, move |store| {
+        registry.register(store)
+    })
+    .map_err(|_| ())
+}
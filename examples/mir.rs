@@ -0,0 +1,50 @@
+#![feature(rustc_private)] // This feature must be added so we can use compiler APIs.
+
+// We need to import them like this otherwise it doesn't work.
+extern crate rustc_middle;
+
+use rustc_middle::mir::{StatementKind, TerminatorKind};
+use rustc_tools::{
+    local_mir_bodies, mir_to_string, with_tyctxt_config, AnalysisConfig, AnalysisLevel,
+};
+
+use std::convert::Infallible;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 1 {
+        eprintln!("Expected one file operand");
+        return;
+    }
+    println!("Running MIR example on `{}`", args[0]);
+    // The MIR is only available if the whole analysis is run.
+    let config = AnalysisConfig::new(&args[0]).level(AnalysisLevel::Full);
+    with_tyctxt_config(&config, |tcx| {
+        for (def_id, body) in local_mir_bodies(tcx) {
+            let (mut statements, mut calls) = (0, 0);
+            for block in body.basic_blocks.iter() {
+                statements += block
+                    .statements
+                    .iter()
+                    .filter(|statement| !matches!(statement.kind, StatementKind::Nop))
+                    .count();
+                if let TerminatorKind::Call { .. } = block.terminator().kind {
+                    calls += 1;
+                }
+            }
+            println!(
+                "=> {}: {} basic blocks, {} statements, {} calls",
+                tcx.def_path_str(def_id),
+                body.basic_blocks.len(),
+                statements,
+                calls,
+            );
+        }
+        // And the textual MIR of the first function, like `-Zunpretty=mir` displays it.
+        if let Some((def_id, _)) = local_mir_bodies(tcx).next() {
+            println!("{}", mir_to_string(tcx, def_id));
+        }
+        Ok::<_, Infallible>(())
+    })
+    .unwrap();
+}
@@ -2,17 +2,36 @@ use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use rustc_ast::ast::Crate;
+use rustc_ast::ast::{Attribute, Crate, Expr, Item, Pat, Stmt, Ty};
+use rustc_ast::node_id::DUMMY_NODE_ID;
+use rustc_ast::token;
+use rustc_ast_pretty::pprust;
 use rustc_data_structures::sync::{Lrc, Send};
 use rustc_errors::emitter::{Emitter, EmitterWriter};
 use rustc_errors::translation::Translate;
-use rustc_errors::{ColorConfig, Diagnostic, Handler, Level as DiagnosticLevel};
-use rustc_parse::new_parser_from_file;
-use rustc_parse::parser::Parser;
+use rustc_errors::{
+    ColorConfig, Diagnostic, DiagnosticBuilder, ErrorGuaranteed, Handler, Level as DiagnosticLevel,
+    PResult,
+};
+use rustc_parse::parser::attr::InnerAttrPolicy;
+use rustc_parse::parser::{
+    AttemptLocalParseRecovery, CommaRecoveryMode, ForceCollect, Parser, RecoverColon, RecoverComma,
+};
+use rustc_parse::{new_parser_from_file, new_parser_from_source_str};
 use rustc_session::parse::ParseSess;
 use rustc_span::edition::Edition;
+use rustc_span::fatal_error::FatalErrorMarker;
 use rustc_span::source_map::{FilePathMapping, SourceMap};
-use rustc_span::FileName;
+use rustc_span::{FileName, SourceFileHashAlgorithm};
+
+use crate::config::{CrateInput, ParseConfig};
+use crate::diagnostics::{
+    CapturedDiagnostic, CapturingEmitter, DiagnosticCollector, DiagnosticOutput,
+};
+use crate::file_loader::normalize;
+use crate::modules::load_modules;
+use crate::recovery::{new_parser_recovering, parse_mod_recovering};
+use crate::Error;
 
 /// You can check `ParseSess` documentation [here](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_session/parse/struct.ParseSess.html)
 /// and `Crate` documentation [here](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_ast/ast/struct.Crate.html).
@@ -20,24 +39,330 @@ use rustc_span::FileName;
 /// And to make things much simpler, I strongly recommend to use
 /// the [AST visitor](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_ast/visit/trait.Visitor.html).
 /// (You can take a look at how to use it with `examples/ast.rs`.)
-pub fn with_ast_parser<T, F: Fn(&ParseSess, &Crate) -> T>(
+///
+/// `diagnostic_output` allows you to choose if the diagnostics should be printed on stderr or
+/// captured (take a look at [`DiagnosticOutput`] for more information).
+///
+/// If the `callback` returns an error, it is returned as [`Error::Other`].
+pub fn with_ast_parser<T, E, F: Fn(&ParseSess, &Crate) -> Result<T, E>>(
     path: &Path,
     edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    let config = ParseConfig::new(path)
+        .edition(edition)
+        .diagnostic_output(diagnostic_output);
+    with_ast_parser_config(&config, callback)
+}
+
+/// Same as [`with_ast_parser`] except that the source code is provided directly as a string
+/// instead of being read from a file. Useful if the code you want to parse hasn't been written
+/// on disk (an unsaved buffer in an editor for example).
+///
+/// `name` is the file name which will be used in the spans and in the emitted diagnostics. You
+/// can get it back with `FileName::from(PathBuf::from(name))` when looking up a `Span` in the
+/// `SourceMap`. The file doesn't need to exist, but the out-of-line modules are looked up
+/// relatively to it.
+pub fn with_ast_parser_from_source<T, E, F: Fn(&ParseSess, &Crate) -> Result<T, E>>(
+    name: &str,
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    let config = ParseConfig::from_source(name, source)
+        .edition(edition)
+        .diagnostic_output(diagnostic_output);
+    with_ast_parser_config(&config, callback)
+}
+
+/// Same as [`with_ast_parser`], but the parser is configured with a [`ParseConfig`]. It allows
+/// to load the out-of-line modules for example (take a look at [`ParseConfig::load_modules`]).
+pub fn with_ast_parser_config<T, E, F: Fn(&ParseSess, &Crate) -> Result<T, E>>(
+    config: &ParseConfig,
+    callback: F,
+) -> Result<T, Error<E>> {
+    parse_with_config(config, false, |parser_session, krate, _| {
+        callback(parser_session, krate)
+    })
+}
+
+/// Same as [`with_ast_parser_config`], but the parser recovers from the errors instead of
+/// stopping at the first one, which is useful for IDE-like tools working on broken code.
+///
+/// The `callback` is called with the partial AST and the errors emitted while parsing (they are
+/// also emitted according to the `diagnostic_output` of `config`). When an item cannot be parsed,
+/// it is missing from the AST and the parser continues with the next one. If the file cannot
+/// even be split into tokens (because of an unclosed delimiter for example), the `callback`
+/// gets an empty crate.
+pub fn with_ast_parser_recovering<
+    T,
+    E,
+    F: Fn(&ParseSess, &Crate, &[CapturedDiagnostic]) -> Result<T, E>,
+>(
+    config: &ParseConfig,
+    callback: F,
+) -> Result<T, Error<E>> {
+    parse_with_config(config, true, |parser_session, krate, errors| {
+        callback(parser_session, krate, &errors.diagnostics())
+    })
+}
+
+fn parse_with_config<T, E>(
+    config: &ParseConfig,
+    recover: bool,
+    callback: impl FnOnce(&ParseSess, &Crate, &DiagnosticCollector) -> Result<T, E>,
+) -> Result<T, Error<E>> {
+    // Errors are always stored so they can be returned in `Error::Parser`.
+    let errors = DiagnosticCollector::new();
+
+    run_parser(config.edition, &errors, || {
+        let (parser_session, can_reset_errors) = create_parser_session(config, errors.clone());
+        let root_path = match &config.input {
+            CrateInput::File(path) => path.clone(),
+            CrateInput::Source { name, .. } => PathBuf::from(name),
+        };
+        let new_parser = || match &config.input {
+            CrateInput::File(path) => new_parser_from_file(&parser_session, path, None),
+            CrateInput::Source { name, source } => new_parser_from_source_str(
+                &parser_session,
+                FileName::from(PathBuf::from(name)),
+                source.clone(),
+            ),
+        };
+        let mut krate = if recover || config.is_ignored(&root_path) {
+            parse_crate_recovering(new_parser_recovering(new_parser))
+        } else {
+            parse_crate(&mut new_parser(), &errors)?
+        };
+        if config.load_modules {
+            if let Err(mut db) =
+                load_modules(&parser_session, config, &mut krate, &root_path, recover)
+            {
+                db.emit();
+                return Err(Error::Parser(errors.take()));
+            }
+        }
+        // All the errors were emitted in ignored files.
+        if can_reset_errors.load(Ordering::Acquire) {
+            parser_session.span_diagnostic.reset_err_count();
+        }
+
+        callback(&parser_session, &krate, &errors).map_err(Error::Other)
+    })
+}
+
+/// Parses `source` as a single expression (like `a + b`) and calls `callback` with it. Useful to
+/// validate a code snippet (generated code for example) without having to wrap it in a file.
+///
+/// Unlike [`with_ast_parser`], the callback isn't called if the parser emitted an error (even if
+/// it recovered from it), and the whole `source` must be consumed. The errors are returned in
+/// [`Error::Parser`], and the spans of the diagnostics point to the `<fragment>` file.
+///
+/// There is one such function per fragment kind: [`with_ty_parser`], [`with_pat_parser`],
+/// [`with_item_parser`], [`with_stmts_parser`] and [`with_attribute_parser`].
+pub fn with_expr_parser<T, E, F: Fn(&ParseSess, &Expr) -> Result<T, E>>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    with_fragment_parser(
+        source,
+        edition,
+        diagnostic_output,
+        |parser| parser.parse_expr(),
+        |sess, expr| callback(sess, expr),
+    )
+}
+
+/// Same as [`with_expr_parser`], but parses a type (like `Vec<u8>`).
+pub fn with_ty_parser<T, E, F: Fn(&ParseSess, &Ty) -> Result<T, E>>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
     callback: F,
-) -> Result<T, String> {
-    let path = PathBuf::from(&path);
+) -> Result<T, Error<E>> {
+    with_fragment_parser(
+        source,
+        edition,
+        diagnostic_output,
+        |parser| parser.parse_ty(),
+        |sess, ty| callback(sess, ty),
+    )
+}
+
+/// Same as [`with_expr_parser`], but parses a pattern (like `Some(x) | None`). Or-patterns are
+/// allowed at the top level.
+pub fn with_pat_parser<T, E, F: Fn(&ParseSess, &Pat) -> Result<T, E>>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    with_fragment_parser(
+        source,
+        edition,
+        diagnostic_output,
+        |parser| {
+            parser.parse_pat_allow_top_alt(
+                None,
+                RecoverComma::No,
+                RecoverColon::No,
+                CommaRecoveryMode::LikelyTuple,
+            )
+        },
+        |sess, pat| callback(sess, pat),
+    )
+}
+
+/// Same as [`with_expr_parser`], but parses an item (like `fn foo() {}`), including its outer
+/// attributes.
+pub fn with_item_parser<T, E, F: Fn(&ParseSess, &Item) -> Result<T, E>>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    with_fragment_parser(
+        source,
+        edition,
+        diagnostic_output,
+        |parser| match parser.parse_item(ForceCollect::No)? {
+            Some(item) => Ok(item),
+            None => Err(expected(parser, "an item")),
+        },
+        |sess, item| callback(sess, item),
+    )
+}
+
+/// Same as [`with_expr_parser`], but parses a list of statements, like the content of a block
+/// (`let a = 1; a + 1`). The last statement can be an expression without a semicolon.
+pub fn with_stmts_parser<T, E, F: Fn(&ParseSess, &[Stmt]) -> Result<T, E>>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    with_fragment_parser(
+        source,
+        edition,
+        diagnostic_output,
+        |parser| {
+            let mut stmts = Vec::new();
+            while parser.token != token::Eof {
+                match parser.parse_full_stmt(AttemptLocalParseRecovery::No)? {
+                    Some(stmt) => stmts.push(stmt),
+                    None => return Err(expected(parser, "a statement")),
+                }
+            }
+            Ok(stmts)
+        },
+        |sess, stmts| callback(sess, stmts),
+    )
+}
+
+/// Same as [`with_expr_parser`], but parses an outer or an inner attribute (like `#[derive(Debug)]`
+/// or `#![allow(dead_code)]`). Doc comments aren't accepted.
+pub fn with_attribute_parser<T, E, F: Fn(&ParseSess, &Attribute) -> Result<T, E>>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    with_fragment_parser(
+        source,
+        edition,
+        diagnostic_output,
+        |parser| {
+            // `parse_attribute` panics if the attribute doesn't start with `#`.
+            if parser.token != token::Pound {
+                return Err(expected(parser, "an attribute"));
+            }
+            parser.parse_attribute(InnerAttrPolicy::Permitted)
+        },
+        |sess, attr| callback(sess, attr),
+    )
+}
 
-    rustc_span::create_session_if_not_set_then(edition, move |_| {
-        let parser_session = create_parser_session();
-        let mut parser = create_parser(&path, &parser_session)?;
-        let krate = parse_crate(&mut parser)?;
+fn with_fragment_parser<N, T, E>(
+    source: &str,
+    edition: Edition,
+    diagnostic_output: DiagnosticOutput,
+    parse: impl for<'a> FnOnce(&mut Parser<'a>) -> PResult<'a, N>,
+    callback: impl FnOnce(&ParseSess, &N) -> Result<T, E>,
+) -> Result<T, Error<E>> {
+    let config = ParseConfig::from_source("fragment", source)
+        .edition(edition)
+        .diagnostic_output(diagnostic_output);
+    let errors = DiagnosticCollector::new();
 
-        Ok(callback(&parser_session, &krate))
+    run_parser(edition, &errors, || {
+        let (parser_session, _) = create_parser_session(&config, errors.clone());
+        let mut parser = new_parser_from_source_str(
+            &parser_session,
+            FileName::Custom("fragment".to_owned()),
+            source.to_owned(),
+        );
+        let node = parse(&mut parser).and_then(|node| {
+            if parser.token == token::Eof {
+                Ok(node)
+            } else {
+                Err(expected(&parser, "the end of the fragment"))
+            }
+        });
+        match node {
+            Ok(node) if parser_session.span_diagnostic.has_errors().is_none() => {
+                callback(&parser_session, &node).map_err(Error::Other)
+            }
+            Ok(_) => Err(Error::Parser(errors.take())),
+            Err(mut db) => {
+                db.emit();
+                Err(Error::Parser(errors.take()))
+            }
+        }
     })
 }
 
-/// Emit errors against every files.
+/// Returns an error for the current token of `parser`.
+pub(crate) fn expected<'a>(
+    parser: &Parser<'a>,
+    what: &str,
+) -> DiagnosticBuilder<'a, ErrorGuaranteed> {
+    parser.sess.span_diagnostic.struct_span_err(
+        parser.token.span,
+        format!(
+            "expected {}, found `{}`",
+            what,
+            pprust::token_to_string(&parser.token)
+        ),
+    )
+}
+
+/// Runs the parser in a new session, and converts the fatal errors (which make the parser panic)
+/// into [`Error::Parser`].
+fn run_parser<T, E>(
+    edition: Edition,
+    errors: &DiagnosticCollector,
+    f: impl FnOnce() -> Result<T, Error<E>>,
+) -> Result<T, Error<E>> {
+    catch_unwind(AssertUnwindSafe(|| {
+        rustc_span::create_session_if_not_set_then(edition, |_| f())
+    }))
+    .unwrap_or_else(|payload| {
+        // If the parser encounters a fatal error, it emits it and then panics with this marker.
+        if payload.is::<FatalErrorMarker>() {
+            Err(Error::Parser(errors.take()))
+        } else {
+            Err(Error::Panic(payload))
+        }
+    })
+}
+
+/// Emit errors against every files except the ignored ones.
 struct SilentOnIgnoredFilesEmitter {
+    ignored_files: Vec<PathBuf>,
     source_map: Lrc<SourceMap>,
     emitter: Box<dyn Emitter + Send>,
     has_non_ignorable_parser_errors: bool,
@@ -72,11 +397,13 @@ impl Emitter for SilentOnIgnoredFilesEmitter {
         }
         if let Some(primary_span) = &db.span.primary_span() {
             let file_name = self.source_map.span_to_filename(*primary_span);
-            if let FileName::Real(rustc_span::RealFileName::LocalPath(_)) = file_name {
-                if !self.has_non_ignorable_parser_errors {
-                    self.can_reset.store(true, Ordering::Release);
+            if let FileName::Real(rustc_span::RealFileName::LocalPath(path)) = file_name {
+                if self.ignored_files.contains(&normalize(&path)) {
+                    if !self.has_non_ignorable_parser_errors {
+                        self.can_reset.store(true, Ordering::Release);
+                    }
+                    return;
                 }
-                return;
             };
         }
         self.handle_non_ignoreable_error(db);
@@ -110,6 +437,9 @@ fn default_handler(
     source_map: Lrc<SourceMap>,
     can_reset: Lrc<AtomicBool>,
     hide_parse_errors: bool,
+    ignored_files: Vec<PathBuf>,
+    diagnostic_output: DiagnosticOutput,
+    errors: DiagnosticCollector,
 ) -> Handler {
     let supports_color = term::stderr().map_or(false, |term| term.supports_color());
     let color_cfg = if supports_color {
@@ -118,16 +448,29 @@ fn default_handler(
         ColorConfig::Never
     };
 
-    let emitter = if hide_parse_errors {
+    let emitter: Box<dyn Emitter + Send> = if hide_parse_errors {
         silent_emitter()
     } else {
-        let fallback_bundle = rustc_errors::fallback_fluent_bundle(
-            rustc_driver::DEFAULT_LOCALE_RESOURCES.to_vec(),
-            false,
-        );
-        Box::new(EmitterWriter::stderr(color_cfg, fallback_bundle))
+        Box::new(CapturingEmitter::new(
+            Lrc::clone(&source_map),
+            // The parser resolves the relative paths against the current directory.
+            std::env::current_dir().unwrap_or_default(),
+            &diagnostic_output,
+            errors,
+            || {
+                let fallback_bundle = rustc_errors::fallback_fluent_bundle(
+                    rustc_driver::DEFAULT_LOCALE_RESOURCES.to_vec(),
+                    false,
+                );
+                Box::new(
+                    EmitterWriter::stderr(color_cfg, fallback_bundle)
+                        .sm(Some(Lrc::clone(&source_map))),
+                )
+            },
+        ))
     };
     Handler::with_emitter(Box::new(SilentOnIgnoredFilesEmitter {
+        ignored_files,
         has_non_ignorable_parser_errors: false,
         source_map,
         emitter,
@@ -135,34 +478,58 @@ fn default_handler(
     }))
 }
 
-fn create_parser_session() -> ParseSess {
-    let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+/// Returns the session and a flag which is `true` if all the errors were emitted in ignored files
+/// (so the error count can be reset).
+fn create_parser_session(
+    config: &ParseConfig,
+    errors: DiagnosticCollector,
+) -> (ParseSess, Lrc<AtomicBool>) {
+    let source_map = Lrc::new(match config.file_loader() {
+        Some(file_loader) => SourceMap::with_file_loader_and_hash_kind(
+            file_loader,
+            FilePathMapping::empty(),
+            SourceFileHashAlgorithm::Md5,
+        ),
+        None => SourceMap::new(FilePathMapping::empty()),
+    });
     let can_reset_errors = Lrc::new(AtomicBool::new(false));
 
     let handler = default_handler(
         Lrc::clone(&source_map),
         Lrc::clone(&can_reset_errors),
-        false,
+        config.hide_parse_errors,
+        config.ignored_files.clone(),
+        config.diagnostic_output.clone(),
+        errors,
     );
-    ParseSess::with_span_handler(handler, source_map)
+    let mut parse_sess = ParseSess::with_span_handler(handler, source_map);
+    parse_sess.config = config.crate_cfg();
+    (parse_sess, can_reset_errors)
 }
 
-fn create_parser<'a>(file: &Path, sess: &'a ParseSess) -> Result<Parser<'a>, String> {
-    catch_unwind(AssertUnwindSafe(move || {
-        new_parser_from_file(sess, file, None)
-    }))
-    .map_err(|e| format!("failed to create parser: {:?}", e))
+fn parse_crate_recovering(parser: Option<Parser<'_>>) -> Crate {
+    let (attrs, items, spans) = match parser {
+        Some(mut parser) => parse_mod_recovering(&mut parser),
+        None => Default::default(),
+    };
+    Crate {
+        attrs,
+        items,
+        spans,
+        id: DUMMY_NODE_ID,
+        is_placeholder: false,
+    }
 }
 
-fn parse_crate(parser: &mut Parser) -> Result<Crate, String> {
-    let mut parser = AssertUnwindSafe(parser);
-
-    match catch_unwind(move || parser.parse_crate_mod()) {
-        Ok(Ok(k)) => Ok(k),
-        Ok(Err(mut db)) => {
+fn parse_crate<E>(
+    parser: &mut Parser<'_>,
+    errors: &DiagnosticCollector,
+) -> Result<Crate, Error<E>> {
+    match parser.parse_crate_mod() {
+        Ok(k) => Ok(k),
+        Err(mut db) => {
             db.emit();
-            Err(String::new())
+            Err(Error::Parser(errors.take()))
         }
-        Err(e) => Err(format!("parser panicked: {:?}", e)),
     }
 }
@@ -0,0 +1,300 @@
+use rustc_driver::{Callbacks, Compilation};
+use rustc_interface::interface::{Compiler, Config};
+use rustc_interface::Queries;
+use rustc_lint::LintStore;
+use rustc_middle::ty::TyCtxt;
+use rustc_session::filesearch;
+use rustc_span::Symbol;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use crate::config::AnalysisConfig;
+use crate::diagnostics::{DiagnosticCollector, DiagnosticOutput};
+use crate::hir::replace_handler;
+use crate::lint::{run_compiler, Lints};
+use crate::lint_config::LintConfig;
+use crate::Error;
+
+/// Environment variable set by the driver when running cargo. Its value identifies the binary of
+/// the driver (it changes when the binary is rebuilt) and every crate compiled with the wrapper
+/// depends on it, so cargo re-runs the wrapper on all workspace crates when the tool changes.
+/// Otherwise, cargo only re-runs it when the crate or one of the tracked files changed, and
+/// replays the diagnostics of the previous run.
+const DRIVER_ID_ENV: &str = "RUSTC_TOOLS_DRIVER_ID";
+
+/// Runs `cargo check` on a crate (or a workspace) with the current binary as
+/// `RUSTC_WORKSPACE_WRAPPER`, the same way `cargo clippy` runs `clippy-driver`. Cargo then calls
+/// the current binary once per workspace crate with the exact arguments it would have provided
+/// to rustc, and the callback provided to [`CargoDriver::with_tyctxt`] or
+/// [`CargoDriver::with_lints`] is called with them. No need to replicate the `cargo build -v`
+/// arguments yourself anymore!
+///
+/// Since the current binary is run by cargo, the `CargoDriver` methods need to be called at the
+/// beginning of your `main` function (take a look at `examples/cargo.rs`):
+///
+/// * When your binary is run by the user, they run `cargo check` and return once it's done.
+/// * When your binary is run by cargo, they run the compiler with the callback on the current
+///   crate. In this case, the manifest path and the cargo arguments are ignored, as well as the
+///   command line arguments of your binary (they're the ones cargo provided for rustc).
+///
+/// If an error is returned, your binary should exit with a non-zero exit code so cargo knows
+/// the compilation failed.
+///
+/// Only the workspace crates are run with the callback, the dependencies are compiled normally
+/// by rustc. Cargo is run with `--offline` by default, so nothing is downloaded and it works fine
+/// with vendored or path dependencies (take a look at [`CargoDriver::offline`]).
+///
+/// Like for `cargo clippy`, cargo doesn't re-run the callback on the crates which didn't change
+/// since the previous run with the same binary: their diagnostics are replayed from its cache
+/// instead. If the callback reads other files, add them to the `tracked_files` of
+/// [`CargoDriver::with_lints`] so cargo re-runs it when they change. The callback of
+/// [`CargoDriver::with_tyctxt`] has no tracked files, so it shouldn't depend on anything else.
+pub struct CargoDriver {
+    manifest_path: PathBuf,
+    cargo_args: Vec<String>,
+    offline: bool,
+    lint_config: Option<LintConfig>,
+}
+
+impl CargoDriver {
+    /// `manifest_path` is the path of the `Cargo.toml` file of the crate to analyze.
+    pub fn new<P: Into<PathBuf>>(manifest_path: P) -> Self {
+        Self {
+            manifest_path: manifest_path.into(),
+            cargo_args: Vec::new(),
+            offline: true,
+            lint_config: None,
+        }
+    }
+
+    /// If `true` (the default), `--offline` is passed to cargo so it doesn't access the network.
+    /// Set it to `false` if the dependencies need to be downloaded.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Adds an argument which will be passed to `cargo check` (`--all-features`, `--target-dir`,
+    /// etc).
+    pub fn cargo_arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.cargo_args.push(arg.into());
+        self
+    }
+
+    /// Sets the levels and the options of the lints run by [`CargoDriver::with_lints`] (take a
+    /// look at [`AnalysisConfig::lint_config`]).
+    pub fn lint_config(mut self, lint_config: LintConfig) -> Self {
+        self.lint_config = Some(lint_config);
+        self
+    }
+
+    /// Returns `true` if the current binary was run by cargo as `RUSTC_WORKSPACE_WRAPPER`.
+    pub fn is_wrapper() -> bool {
+        std::env::var_os(DRIVER_ID_ENV).is_some() && std::env::args_os().nth(1).is_some()
+    }
+
+    /// Equivalent of [`with_tyctxt`](crate::with_tyctxt) for all workspace crates. Unlike
+    /// [`with_tyctxt`](crate::with_tyctxt), the compilation is done completely (it needs to
+    /// generate the metadata for the crates depending on the current one), so the `callback` is
+    /// called once the whole analysis has been done.
+    pub fn with_tyctxt<E: Send, F: FnOnce(TyCtxt<'_>) -> Result<(), E> + Send>(
+        &self,
+        callback: F,
+    ) -> Result<(), Error<E>> {
+        let Some(args) = wrapper_args() else {
+            return self.run_cargo();
+        };
+        if is_rustc_query(&args) {
+            return run_rustc(&args);
+        }
+        let errors = DiagnosticCollector::new();
+        let mut callbacks = TrackDriverId(TyCtxtCallbacks {
+            callback: Some(callback),
+            result: Ok(()),
+            errors: errors.clone(),
+        });
+        run_compiler(&args, &mut callbacks, &errors)?;
+        callbacks.0.result.map_err(Error::Other)
+    }
+
+    /// Equivalent of [`with_lints`](crate::with_lints) for all workspace crates.
+    pub fn with_lints<F: Fn(&mut LintStore) + Send + Sync + 'static>(
+        &self,
+        tracked_files: Vec<String>,
+        callback: F,
+    ) -> Result<(), Error> {
+        let Some(args) = wrapper_args() else {
+            return self.run_cargo();
+        };
+        if is_rustc_query(&args) {
+            return run_rustc(&args);
+        }
+        let errors = DiagnosticCollector::new();
+        let mut config = AnalysisConfig::from_args(&[]);
+        config.tracked_files = tracked_files;
+        if let Some(lint_config) = &self.lint_config {
+            config = config.lint_config(lint_config.clone());
+        }
+        let mut lints = TrackDriverId(Lints::new(callback, &config, errors.clone()));
+        run_compiler(&args, &mut lints, &errors)
+    }
+
+    fn run_cargo<E>(&self) -> Result<(), Error<E>> {
+        let current_exe = std::env::current_exe()
+            .map_err(|e| Error::Cargo(format!("cannot get current executable: {}", e)))?;
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+
+        let mut command = Command::new(cargo);
+        command
+            .arg("check")
+            .arg("--manifest-path")
+            .arg(&self.manifest_path)
+            .args(&self.cargo_args)
+            .env(DRIVER_ID_ENV, driver_id(&current_exe))
+            .env("RUSTC_WORKSPACE_WRAPPER", current_exe);
+        if self.offline {
+            command.arg("--offline");
+        }
+        // The crates need to be compiled by the same rustc version as the one we're linked to,
+        // otherwise we won't be able to read their metadata.
+        if let Ok(sysroot) = filesearch::get_or_default_sysroot() {
+            command.env("RUSTC", sysroot.join("bin").join("rustc"));
+        }
+
+        let status = command
+            .status()
+            .map_err(|e| Error::Cargo(format!("cannot run cargo: {}", e)))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::Cargo(status.to_string()))
+        }
+    }
+}
+
+/// Returns an identifier of the binary at `path`, which changes when it's rebuilt.
+fn driver_id(path: &Path) -> String {
+    let Ok(metadata) = path.metadata() else {
+        return String::new();
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_nanos())
+        .unwrap_or_default();
+    format!("{}-{}", modified, metadata.len())
+}
+
+/// Returns the rustc arguments (the first one being the rustc path) if we're running as wrapper.
+fn wrapper_args() -> Option<Vec<String>> {
+    if !CargoDriver::is_wrapper() {
+        return None;
+    }
+    Some(std::env::args().skip(1).collect())
+}
+
+/// Cargo also uses the wrapper to query information from rustc (its version, the target
+/// information, etc). In this case, we don't want to run the compiler ourselves.
+fn is_rustc_query(args: &[String]) -> bool {
+    args.iter().skip(1).any(|arg| {
+        arg == "-"
+            || arg == "-vV"
+            || arg == "-V"
+            || arg == "--version"
+            || arg.starts_with("--print")
+    })
+}
+
+fn run_rustc<E>(args: &[String]) -> Result<(), Error<E>> {
+    let status = Command::new(&args[0])
+        .args(&args[1..])
+        .status()
+        .map_err(|e| Error::Cargo(format!("failed to run `{}`: {}", args[0], e)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Cargo(format!("`{}` failed: {}", args[0], status)))
+    }
+}
+
+/// Calls the `with_tyctxt` callback once the analysis is done.
+struct TyCtxtCallbacks<F, E> {
+    callback: Option<F>,
+    result: Result<(), E>,
+    errors: DiagnosticCollector,
+}
+
+impl<E: Send, F: FnOnce(TyCtxt<'_>) -> Result<(), E> + Send> Callbacks for TyCtxtCallbacks<F, E> {
+    fn config(&mut self, config: &mut Config) {
+        let replace_handler =
+            replace_handler(&config.opts, DiagnosticOutput::Stderr, self.errors.clone());
+        config.parse_sess_created = Some(Box::new(replace_handler));
+    }
+
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        let Some(callback) = self.callback.take() else {
+            return Compilation::Continue;
+        };
+        queries.global_ctxt().unwrap().enter(|tcx| {
+            self.result = callback(tcx);
+        });
+        if self.result.is_ok() {
+            Compilation::Continue
+        } else {
+            Compilation::Stop
+        }
+    }
+}
+
+/// Makes the compiled crate depend on [`DRIVER_ID_ENV`] so cargo re-runs the wrapper when the
+/// binary of the driver changes.
+struct TrackDriverId<C>(C);
+
+impl<C: Callbacks> Callbacks for TrackDriverId<C> {
+    fn config(&mut self, config: &mut Config) {
+        self.0.config(config);
+
+        let previous = config.parse_sess_created.take();
+        let driver_id = std::env::var(DRIVER_ID_ENV).ok();
+        config.parse_sess_created = Some(Box::new(move |parse_sess| {
+            if let Some(previous) = previous {
+                previous(parse_sess);
+            }
+            parse_sess.env_depinfo.get_mut().insert((
+                Symbol::intern(DRIVER_ID_ENV),
+                driver_id.as_deref().map(Symbol::intern),
+            ));
+        }));
+    }
+
+    fn after_crate_root_parsing<'tcx>(
+        &mut self,
+        compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        self.0.after_crate_root_parsing(compiler, queries)
+    }
+
+    fn after_expansion<'tcx>(
+        &mut self,
+        compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        self.0.after_expansion(compiler, queries)
+    }
+
+    fn after_analysis<'tcx>(
+        &mut self,
+        compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        self.0.after_analysis(compiler, queries)
+    }
+}
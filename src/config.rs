@@ -0,0 +1,661 @@
+use rustc_errors::registry::Registry;
+use rustc_interface::util::rustc_version_str;
+use rustc_session::config::{
+    build_configuration, CrateType, ErrorOutputType, ExternEntry, ExternLocation, Externs, Input,
+    Options,
+};
+use rustc_session::parse::CrateConfig;
+use rustc_session::search_paths::{PathKind, SearchPath, SearchPathFile};
+use rustc_session::utils::CanonicalizedPath;
+use rustc_session::{build_session, CompilerIO, EarlyErrorHandler};
+use rustc_span::edition::{Edition, DEFAULT_EDITION};
+use rustc_span::source_map::FileLoader;
+use rustc_span::{FileName, Symbol};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use crate::diagnostics::DiagnosticOutput;
+use crate::file_loader::{normalize, VirtualFileLoader, VirtualFiles};
+use crate::lint_config::LintConfig;
+use crate::queries::{QueryOverride, QueryOverrides};
+
+/// Format of the diagnostics printed on stderr (`--error-format` rustc option).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// The default rustc output.
+    Human,
+    /// Only one line per diagnostic.
+    Short,
+    /// One JSON object per diagnostic, like when the compiler is run by cargo.
+    Json,
+}
+
+impl ErrorFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Human => "human",
+            Self::Short => "short",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// How far the compiler goes before calling the [`with_tyctxt_config`](crate::with_tyctxt_config)
+/// callback. If you only need the syntax, take a look at
+/// [`with_ast_parser`](crate::with_ast_parser) instead.
+///
+/// Each level runs everything the previous ones run. If the compiler emits an error, the callback
+/// isn't called.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnalysisLevel {
+    /// Macros are expanded and names are resolved, nothing is checked. The HIR is lowered when
+    /// you first access it.
+    Expanded,
+    /// The HIR is lowered and the attributes are checked.
+    Hir,
+    /// The types of the items (functions signatures, structs fields, etc) are collected and
+    /// checked, but not the function bodies.
+    #[default]
+    ItemTypes,
+    /// The bodies are type checked too, so `tcx.typeck` results can be used.
+    TypeCheck,
+    /// Everything `rustc --emit=metadata` does: type checking, borrow checking and lints. The MIR
+    /// of the local functions is available (take a look at
+    /// [`local_mir_bodies`](crate::local_mir_bodies)).
+    ///
+    /// The MIR passes steal the MIR they transform, so at this level `tcx.mir_built` and
+    /// `tcx.mir_promoted` panic with "attempted to read from stolen value". To look at the MIR as
+    /// it's built, use [`AnalysisLevel::TypeCheck`] (nothing is stolen until the optimized MIR or
+    /// the borrow checker results are requested), or wrap the `mir_built` provider with
+    /// [`AnalysisConfig::override_queries`] to see each body before it's stolen.
+    Full,
+}
+
+/// A crate passed with `--extern`.
+#[derive(Clone, Debug)]
+struct ExternCrate {
+    name: String,
+    /// `None` if the crate is looked up in the search paths.
+    path: Option<PathBuf>,
+    /// `false` for `noprelude:`.
+    add_prelude: bool,
+}
+
+/// The options of an [`AnalysisConfig`] which contain paths. They're set on the compiler
+/// `Options` directly instead of being converted into command line arguments, so the paths
+/// don't need to be valid UTF-8.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathOptions {
+    externs: Vec<ExternCrate>,
+    search_paths: Vec<(PathKind, PathBuf)>,
+    sysroot: Option<PathBuf>,
+}
+
+impl PathOptions {
+    /// Adds the options to `opts` (after the ones of the command line arguments).
+    pub(crate) fn apply(&self, opts: &mut Options) {
+        if let Some(sysroot) = &self.sysroot {
+            opts.maybe_sysroot = Some(sysroot.clone());
+        }
+        for (kind, dir) in &self.search_paths {
+            opts.search_paths.push(search_path(*kind, dir.clone()));
+        }
+        if self.externs.is_empty() {
+            return;
+        }
+        let mut externs = opts
+            .externs
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect::<BTreeMap<_, _>>();
+        for extern_crate in &self.externs {
+            // Same as `rustc_session::config::parse_externs`.
+            let entry = externs
+                .entry(extern_crate.name.clone())
+                .or_insert_with(|| ExternEntry {
+                    location: ExternLocation::FoundInLibrarySearchDirectories,
+                    is_private_dep: false,
+                    add_prelude: false,
+                    nounused_dep: false,
+                    force: false,
+                });
+            if let Some(path) = &extern_crate.path {
+                let path = CanonicalizedPath::new(path);
+                match &mut entry.location {
+                    ExternLocation::ExactPaths(files) => {
+                        files.insert(path);
+                    }
+                    location => *location = ExternLocation::ExactPaths(BTreeSet::from([path])),
+                }
+            }
+            entry.add_prelude |= extern_crate.add_prelude;
+        }
+        opts.externs = Externs::new(externs);
+    }
+}
+
+/// Same as `SearchPath::new`, which is private.
+fn search_path(kind: PathKind, dir: PathBuf) -> SearchPath {
+    let files = std::fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|entry| {
+                    let file_name_str = entry.file_name().to_str()?.to_owned();
+                    Some(SearchPathFile {
+                        path: entry.path(),
+                        file_name_str,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    SearchPath { kind, dir, files }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum CrateInput {
+    File(PathBuf),
+    Source { name: String, source: String },
+}
+
+/// Typed configuration of the compiler used by [`with_tyctxt_config`](crate::with_tyctxt_config)
+/// and [`with_lints_config`](crate::with_lints_config), so you don't need to write the rustc
+/// command line arguments yourself:
+///
+/// ```ignore
+/// let config = AnalysisConfig::new("src/lib.rs")
+///     .edition(Edition::Edition2021)
+///     .crate_type(CrateType::Rlib)
+///     .cfg_value("feature", "std")
+///     .extern_crate("helper", "target/debug/deps/libhelper.rlib")
+///     .search_path(PathKind::Dependency, "target/debug/deps");
+/// ```
+///
+/// Options which don't have a method can still be passed with [`AnalysisConfig::arg`].
+#[derive(Clone)]
+pub struct AnalysisConfig {
+    input: Option<CrateInput>,
+    edition: Option<Edition>,
+    cfgs: Vec<String>,
+    check_cfgs: Vec<String>,
+    path_options: PathOptions,
+    crate_name: Option<String>,
+    crate_types: Vec<CrateType>,
+    target: Option<String>,
+    error_format: Option<ErrorFormat>,
+    args: Vec<String>,
+    virtual_files: VirtualFiles,
+    pub(crate) level: AnalysisLevel,
+    query_overrides: Vec<QueryOverride>,
+    pub(crate) tracked_files: Vec<String>,
+    pub(crate) diagnostic_output: DiagnosticOutput,
+    pub(crate) lint_config: Option<LintConfig>,
+}
+
+impl AnalysisConfig {
+    fn with_input(input: Option<CrateInput>) -> Self {
+        Self {
+            input,
+            edition: None,
+            cfgs: Vec::new(),
+            check_cfgs: Vec::new(),
+            path_options: PathOptions::default(),
+            crate_name: None,
+            crate_types: Vec::new(),
+            target: None,
+            error_format: None,
+            args: Vec::new(),
+            virtual_files: VirtualFiles::default(),
+            level: AnalysisLevel::default(),
+            query_overrides: Vec::new(),
+            tracked_files: Vec::new(),
+            diagnostic_output: DiagnosticOutput::default(),
+            lint_config: None,
+        }
+    }
+
+    /// Analyzes the crate whose root is the file at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self::with_input(Some(CrateInput::File(path.into())))
+    }
+
+    /// Analyzes the crate whose root is `source`. `name` is the file name used in the
+    /// diagnostics, and `mod` declarations are looked up relatively to it.
+    pub fn from_source<N: Into<String>, S: Into<String>>(name: N, source: S) -> Self {
+        Self::with_input(Some(CrateInput::Source {
+            name: name.into(),
+            source: source.into(),
+        }))
+    }
+
+    /// Uses rustc command line arguments (without the binary name). It's what is used by
+    /// [`with_tyctxt`](crate::with_tyctxt) and [`with_lints`](crate::with_lints). The input file
+    /// is expected to be part of `args`.
+    pub fn from_args(args: &[String]) -> Self {
+        let mut config = Self::with_input(None);
+        config.args = args.to_vec();
+        config
+    }
+
+    /// Sets the edition of the crate (`--edition`).
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.edition = Some(edition);
+        self
+    }
+
+    /// Enables the `name` cfg (`--cfg name`).
+    pub fn cfg<S: Into<String>>(mut self, name: S) -> Self {
+        self.cfgs.push(name.into());
+        self
+    }
+
+    /// Enables the `name = "value"` cfg (`--cfg 'name="value"'`).
+    pub fn cfg_value<N: AsRef<str>, V: AsRef<str>>(mut self, name: N, value: V) -> Self {
+        self.cfgs
+            .push(format!("{}={:?}", name.as_ref(), value.as_ref()));
+        self
+    }
+
+    /// Adds a `--check-cfg` specification (like `cfg(feature, values("std"))`). Don't forget that
+    /// this option requires `-Zunstable-options`.
+    pub fn check_cfg<S: Into<String>>(mut self, spec: S) -> Self {
+        self.check_cfgs.push(spec.into());
+        self
+    }
+
+    /// Makes the crate `name` available from the compiled metadata at `path` (`--extern`). It
+    /// can be called several times with the same `name`: the compiler then picks the right file
+    /// among them.
+    pub fn extern_crate<N: Into<String>, P: Into<PathBuf>>(mut self, name: N, path: P) -> Self {
+        self.path_options.externs.push(ExternCrate {
+            name: name.into(),
+            path: Some(path.into()),
+            add_prelude: true,
+        });
+        self
+    }
+
+    /// Makes the crate `name` available, the compiler looks it up in the search paths
+    /// (`--extern name`, for `proc_macro` for example).
+    pub fn extern_crate_from_search_paths<N: Into<String>>(mut self, name: N) -> Self {
+        self.path_options.externs.push(ExternCrate {
+            name: name.into(),
+            path: None,
+            add_prelude: true,
+        });
+        self
+    }
+
+    /// Same as [`AnalysisConfig::extern_crate`], but the crate isn't added to the extern prelude
+    /// (`--extern noprelude:name=path`), so it's only available with `extern crate name;`.
+    pub fn extern_crate_noprelude<N: Into<String>, P: Into<PathBuf>>(
+        mut self,
+        name: N,
+        path: P,
+    ) -> Self {
+        self.path_options.externs.push(ExternCrate {
+            name: name.into(),
+            path: Some(path.into()),
+            add_prelude: false,
+        });
+        self
+    }
+
+    /// Adds a directory where the compiler looks for the dependencies (`-L`).
+    pub fn search_path<P: Into<PathBuf>>(mut self, kind: PathKind, path: P) -> Self {
+        self.path_options.search_paths.push((kind, path.into()));
+        self
+    }
+
+    /// Sets the name of the crate (`--crate-name`).
+    pub fn crate_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.crate_name = Some(name.into());
+        self
+    }
+
+    /// Adds a type to the crate (`--crate-type`).
+    pub fn crate_type(mut self, crate_type: CrateType) -> Self {
+        self.crate_types.push(crate_type);
+        self
+    }
+
+    /// Sets the target triple (`--target`).
+    pub fn target<S: Into<String>>(mut self, triple: S) -> Self {
+        self.target = Some(triple.into());
+        self
+    }
+
+    /// Sets the sysroot (`--sysroot`). By default, the one of the toolchain is used.
+    pub fn sysroot<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path_options.sysroot = Some(path.into());
+        self
+    }
+
+    /// Sets the format of the diagnostics printed on stderr (`--error-format`).
+    pub fn error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = Some(error_format);
+        self
+    }
+
+    /// Adds a raw rustc argument, for the options which don't have a method.
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Returns `true` if one of the raw arguments matches `predicate`.
+    pub(crate) fn has_arg<F: Fn(&str) -> bool>(&self, predicate: F) -> bool {
+        self.args.iter().any(|arg| predicate(arg))
+    }
+
+    /// Provides the content of the file at `path` so the compiler doesn't read it from the disk.
+    /// It allows `mod` declarations (and `include_str!` and co) to use unsaved editor buffers or
+    /// generated files. The files which aren't provided are still read from the disk.
+    ///
+    /// The path is looked up as the compiler builds it, so it needs to be relative to the same
+    /// directory as the crate root: if the crate root is `src/lib.rs`, the `mod foo;` declaration
+    /// will look for `src/foo.rs` and `src/foo/mod.rs`. The crate root can be a virtual file too.
+    pub fn virtual_file<P: Into<PathBuf>, S: Into<String>>(mut self, path: P, content: S) -> Self {
+        Arc::make_mut(&mut self.virtual_files).insert(normalize(&path.into()), content.into());
+        self
+    }
+
+    /// Sets how far the compiler goes before calling the
+    /// [`with_tyctxt_config`](crate::with_tyctxt_config) callback. The default is
+    /// [`AnalysisLevel::ItemTypes`].
+    pub fn level(mut self, level: AnalysisLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Adds a function overriding rustc query providers, like `override_queries` in the rustc
+    /// configuration. It's called after the ones of this crate (so you can replace them) and
+    /// after the previously added ones. It allows to replace `mir_borrowck` to get the borrow
+    /// checker facts for example.
+    ///
+    /// To wrap a provider, you can call the default one from
+    /// [`DEFAULT_QUERY_PROVIDERS`](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_interface/passes/static.DEFAULT_QUERY_PROVIDERS.html).
+    pub fn override_queries(mut self, query_override: QueryOverride) -> Self {
+        self.query_overrides.push(query_override);
+        self
+    }
+
+    /// Adds files which will trigger a re-compilation if they are modified (only used by
+    /// [`with_lints_config`](crate::with_lints_config)).
+    pub fn tracked_file<S: Into<String>>(mut self, path: S) -> Self {
+        self.tracked_files.push(path.into());
+        self
+    }
+
+    /// Chooses if the diagnostics should be printed on stderr or captured (take a look at
+    /// [`DiagnosticOutput`] for more information).
+    pub fn diagnostic_output(mut self, diagnostic_output: DiagnosticOutput) -> Self {
+        self.diagnostic_output = diagnostic_output;
+        self
+    }
+
+    /// Sets the levels and the options of the lints (only used by
+    /// [`with_lints_config`](crate::with_lints_config)). If the configuration comes from a file, it
+    /// is added to the tracked files, so cargo re-runs the lints when it is modified.
+    pub fn lint_config(mut self, lint_config: LintConfig) -> Self {
+        if let Some(path) = lint_config.path() {
+            self.tracked_files.push(path.display().to_string());
+        }
+        self.lint_config = Some(lint_config);
+        self
+    }
+
+    /// Returns the rustc command line arguments (without the binary name) of the options which
+    /// don't contain paths. The other ones need to be set with [`AnalysisConfig::path_options`],
+    /// and the input with [`AnalysisConfig::input`].
+    pub(crate) fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(edition) = self.edition {
+            args.push(format!("--edition={}", edition));
+        }
+        for cfg in &self.cfgs {
+            args.extend(["--cfg".to_owned(), cfg.clone()]);
+        }
+        for spec in &self.check_cfgs {
+            args.extend(["--check-cfg".to_owned(), spec.clone()]);
+        }
+        if let Some(crate_name) = &self.crate_name {
+            args.extend(["--crate-name".to_owned(), crate_name.clone()]);
+        }
+        for crate_type in &self.crate_types {
+            args.extend(["--crate-type".to_owned(), crate_type.to_string()]);
+        }
+        if let Some(target) = &self.target {
+            args.extend(["--target".to_owned(), target.clone()]);
+        }
+        if let Some(error_format) = self.error_format {
+            args.push(format!("--error-format={}", error_format.as_str()));
+        }
+        args.extend(self.args.iter().cloned());
+        // The input is replaced once the arguments are parsed, the compiler just needs one.
+        match &self.input {
+            Some(CrateInput::File(path)) => args.push(path.to_string_lossy().into_owned()),
+            Some(CrateInput::Source { name, .. }) => args.push(name.clone()),
+            None => {}
+        }
+        args
+    }
+
+    /// Returns the file loader to use if there are virtual files.
+    pub(crate) fn file_loader(&self) -> Option<Box<dyn FileLoader + Send + Sync>> {
+        file_loader(&self.virtual_files)
+    }
+
+    /// Returns the query overrides for this configuration. If `disable_typeck` is `true`, the
+    /// queries requiring the bodies to be type checked are disabled.
+    pub(crate) fn query_overrides(&self, disable_typeck: bool) -> QueryOverrides {
+        QueryOverrides {
+            disable_typeck,
+            user: self.query_overrides.clone(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Returns the input which needs to replace the one parsed from the arguments, if any.
+    pub(crate) fn input(&self) -> Option<Input> {
+        match &self.input {
+            Some(CrateInput::File(path)) => Some(Input::File(path.clone())),
+            Some(CrateInput::Source { name, source }) => Some(Input::Str {
+                name: FileName::from(PathBuf::from(name)),
+                input: source.clone(),
+            }),
+            None => None,
+        }
+    }
+
+    pub(crate) fn path_options(&self) -> &PathOptions {
+        &self.path_options
+    }
+}
+
+fn file_loader(virtual_files: &VirtualFiles) -> Option<Box<dyn FileLoader + Send + Sync>> {
+    if virtual_files.is_empty() {
+        return None;
+    }
+    Some(Box::new(VirtualFileLoader::new(Arc::clone(virtual_files))))
+}
+
+/// Configuration of the parser used by [`with_ast_parser_config`](crate::with_ast_parser_config):
+///
+/// ```ignore
+/// let config = ParseConfig::new("src/lib.rs")
+///     .edition(Edition::Edition2021)
+///     .cfg_value("feature", "std")
+///     .load_modules(true);
+/// ```
+#[derive(Clone)]
+pub struct ParseConfig {
+    pub(crate) input: CrateInput,
+    pub(crate) edition: Edition,
+    cfgs: Vec<(String, Option<String>)>,
+    pub(crate) load_modules: bool,
+    virtual_files: VirtualFiles,
+    pub(crate) diagnostic_output: DiagnosticOutput,
+    pub(crate) hide_parse_errors: bool,
+    pub(crate) ignored_files: Vec<PathBuf>,
+}
+
+impl ParseConfig {
+    fn with_input(input: CrateInput) -> Self {
+        Self {
+            input,
+            edition: DEFAULT_EDITION,
+            cfgs: Vec::new(),
+            load_modules: false,
+            virtual_files: VirtualFiles::default(),
+            diagnostic_output: DiagnosticOutput::default(),
+            hide_parse_errors: false,
+            ignored_files: Vec::new(),
+        }
+    }
+
+    /// Parses the file at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self::with_input(CrateInput::File(path.into()))
+    }
+
+    /// Parses `source`. `name` is the file name used in the spans and in the diagnostics, and `mod`
+    /// declarations are looked up relatively to it. You can get it back with
+    /// `FileName::from(PathBuf::from(name))` when looking up a `Span` in the `SourceMap`.
+    pub fn from_source<N: Into<String>, S: Into<String>>(name: N, source: S) -> Self {
+        Self::with_input(CrateInput::Source {
+            name: name.into(),
+            source: source.into(),
+        })
+    }
+
+    /// Sets the edition used to parse the code. The default is the rustc one (2015).
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    /// Enables the `name` cfg, in addition to the ones rustc enables for the host target. The cfgs
+    /// are only used to know which modules to load (take a look at [`ParseConfig::load_modules`]).
+    pub fn cfg<S: Into<String>>(mut self, name: S) -> Self {
+        self.cfgs.push((name.into(), None));
+        self
+    }
+
+    /// Enables the `name = "value"` cfg, in addition to the ones rustc enables for the host
+    /// target. The cfgs are only used to know which modules to load (take a look at
+    /// [`ParseConfig::load_modules`]).
+    pub fn cfg_value<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.cfgs.push((name.into(), Some(value.into())));
+        self
+    }
+
+    /// If `true`, the out-of-line modules (`mod foo;`) are loaded recursively the same way rustc
+    /// does, so the callback gets the AST of the whole crate. The `#[path]` attributes are
+    /// honored, as well as the `#[cfg_attr(..., path = "...")]` ones. The modules disabled by a
+    /// `#[cfg]` attribute are not loaded. The enabled cfgs are the ones rustc enables by default
+    /// for the host target (`unix`, `target_os = "linux"`, `debug_assertions`, etc) and the ones
+    /// added with [`ParseConfig::cfg`] and [`ParseConfig::cfg_value`]. Nothing else is done: the
+    /// macros aren't expanded and the other `cfg` attributes are kept.
+    ///
+    /// If the crate is parsed from a string, the modules are looked up relatively to its name.
+    pub fn load_modules(mut self, load_modules: bool) -> Self {
+        self.load_modules = load_modules;
+        self
+    }
+
+    /// Provides the content of the file at `path` so the parser doesn't read it from the disk
+    /// (take a look at [`AnalysisConfig::virtual_file`] for more information).
+    pub fn virtual_file<P: Into<PathBuf>, S: Into<String>>(mut self, path: P, content: S) -> Self {
+        Arc::make_mut(&mut self.virtual_files).insert(normalize(&path.into()), content.into());
+        self
+    }
+
+    /// Chooses if the diagnostics should be printed on stderr or captured (take a look at
+    /// [`DiagnosticOutput`] for more information).
+    pub fn diagnostic_output(mut self, diagnostic_output: DiagnosticOutput) -> Self {
+        self.diagnostic_output = diagnostic_output;
+        self
+    }
+
+    /// If `true`, the diagnostics are neither printed nor captured, whatever the
+    /// `diagnostic_output` is. Parsing still fails on errors, but [`Error::Parser`] will be empty.
+    /// Useful for batch tools which only care about the files which can be parsed.
+    ///
+    /// [`Error::Parser`]: crate::Error::Parser
+    pub fn hide_parse_errors(mut self, hide_parse_errors: bool) -> Self {
+        self.hide_parse_errors = hide_parse_errors;
+        self
+    }
+
+    /// Silences the diagnostics located in the file at `path` (generated or vendored code for
+    /// example). The file is parsed as if [`with_ast_parser_recovering`] was used, so its errors
+    /// don't make the parsing fail. If all the errors come from ignored files, the error count of
+    /// the `ParseSess` given to the callback is reset, so it looks like there was no error. The
+    /// diagnostics of the other files are always emitted.
+    ///
+    /// `path` must be the path used to load the file: the input path for the crate root, or its
+    /// directory joined with the module path for the modules (like `src/foo/bar.rs`).
+    ///
+    /// [`with_ast_parser_recovering`]: crate::with_ast_parser_recovering
+    pub fn ignored_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ignored_files.push(normalize(&path.into()));
+        self
+    }
+
+    /// Returns the file loader to use if there are virtual files.
+    pub(crate) fn file_loader(&self) -> Option<Box<dyn FileLoader + Send + Sync>> {
+        file_loader(&self.virtual_files)
+    }
+
+    /// Returns `true` if `path` has been ignored with [`ParseConfig::ignored_file`].
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        self.ignored_files.contains(&normalize(path))
+    }
+
+    /// Returns the cfgs as expected by `ParseSess`, including the default ones of the host target.
+    pub(crate) fn crate_cfg(&self) -> CrateConfig {
+        DEFAULT_CFGS
+            .iter()
+            .chain(&self.cfgs)
+            .map(|(name, value)| (Symbol::intern(name), value.as_deref().map(Symbol::intern)))
+            .collect()
+    }
+}
+
+/// The cfgs rustc enables by default for the host target, without any option. They're stored as
+/// strings because the `Symbol`s only live as long as the compiler session globals.
+static DEFAULT_CFGS: LazyLock<Vec<(String, Option<String>)>> = LazyLock::new(|| {
+    // The cfgs come from the target of a session, so a session is created just for that.
+    let handler = EarlyErrorHandler::new(ErrorOutputType::default());
+    let io = CompilerIO {
+        input: Input::Str {
+            name: FileName::Custom(String::new()),
+            input: String::new(),
+        },
+        output_dir: None,
+        output_file: None,
+        temps_dir: None,
+    };
+    let sess = build_session(
+        &handler,
+        Options::default(),
+        io,
+        None,
+        Registry::new(&[]),
+        rustc_driver::DEFAULT_LOCALE_RESOURCES.to_vec(),
+        Default::default(),
+        None,
+        None,
+        rustc_version_str().unwrap_or("unknown"),
+        None,
+        Vec::new(),
+    );
+    build_configuration(&sess, CrateConfig::default())
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.map(|value| value.to_string())))
+        .collect()
+});
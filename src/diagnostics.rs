@@ -0,0 +1,449 @@
+use rustc_data_structures::marker::DynSend;
+use rustc_data_structures::sync::Lrc;
+use rustc_error_messages::{DiagnosticMessage, FluentArgs};
+use rustc_errors::emitter::{Emitter, EmitterWriter};
+use rustc_errors::translation::{to_fluent_args, Translate};
+use rustc_errors::{
+    Applicability, Diagnostic, DiagnosticId, FluentBundle, LazyFallbackBundle, Level, MultiSpan,
+    Style,
+};
+use rustc_lint_defs::Level as LintLevel;
+use rustc_span::source_map::SourceMap;
+use rustc_span::{FileName, Span};
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::sarif::SarifCollector;
+
+/// Where the diagnostics emitted while running an entry point should go.
+#[derive(Clone, Default)]
+pub enum DiagnosticOutput {
+    /// Diagnostics are printed on stderr, just like rustc does.
+    #[default]
+    Stderr,
+    /// Diagnostics are not printed but stored into the given [`DiagnosticCollector`] instead.
+    Capture(DiagnosticCollector),
+    /// Diagnostics are not printed but stored into the given [`SarifCollector`], along with the
+    /// metadata of the registered lints, so they can be converted into a SARIF log.
+    Sarif(SarifCollector),
+}
+
+/// Stores the diagnostics emitted when using [`DiagnosticOutput::Capture`]. It can be cloned
+/// freely, all clones share the same storage.
+#[derive(Clone, Default)]
+pub struct DiagnosticCollector {
+    diagnostics: Arc<Mutex<Vec<CapturedDiagnostic>>>,
+}
+
+impl DiagnosticCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns all the diagnostics captured so far and empties the collector.
+    pub fn take(&self) -> Vec<CapturedDiagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock().unwrap())
+    }
+
+    /// Returns a copy of all the diagnostics captured so far.
+    pub fn diagnostics(&self) -> Vec<CapturedDiagnostic> {
+        self.diagnostics.lock().unwrap().clone()
+    }
+
+    pub(crate) fn push(&self, diagnostic: CapturedDiagnostic) {
+        self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+}
+
+/// Level of a [`CapturedDiagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticLevel {
+    /// Internal compiler error.
+    Bug,
+    Fatal,
+    Error,
+    Warning,
+    Note,
+    Help,
+    FailureNote,
+}
+
+impl DiagnosticLevel {
+    fn from_level(level: Level) -> Self {
+        match level {
+            Level::Bug | Level::DelayedBug => Self::Bug,
+            Level::Fatal => Self::Fatal,
+            Level::Error { .. } => Self::Error,
+            Level::Warning(_) | Level::Allow | Level::Expect(_) => Self::Warning,
+            Level::Note | Level::OnceNote => Self::Note,
+            Level::Help | Level::OnceHelp => Self::Help,
+            Level::FailureNote => Self::FailureNote,
+        }
+    }
+
+    /// Returns the same string as the one displayed by rustc.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Bug => "error: internal compiler error",
+            Self::Fatal | Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+            Self::Help => "help",
+            Self::FailureNote => "failure-note",
+        }
+    }
+
+    /// Returns `true` if this level is an error (fatal or not).
+    pub fn is_error(self) -> bool {
+        matches!(self, Self::Bug | Self::Fatal | Self::Error)
+    }
+}
+
+/// Code associated to a [`CapturedDiagnostic`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    /// An error code like `E0308`.
+    Error(String),
+    /// The name of the lint which emitted the diagnostic (`dead_code`, `clippy::all`, etc).
+    Lint(String),
+}
+
+/// A `Span` resolved into its file and position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedSpan {
+    pub file_name: String,
+    /// Path of the file on disk, resolved against the working directory of the compiler (so it
+    /// doesn't depend on the current directory of the process). `None` if the file doesn't come
+    /// from a path.
+    pub local_path: Option<PathBuf>,
+    /// Start of the span in bytes, relative to the start of the file.
+    pub byte_start: u32,
+    /// End of the span in bytes, relative to the start of the file.
+    pub byte_end: u32,
+    /// 1-based.
+    pub line_start: usize,
+    /// 1-based.
+    pub line_end: usize,
+    /// 1-based, in characters.
+    pub column_start: usize,
+    /// 1-based, in characters.
+    pub column_end: usize,
+    /// `true` if this is the "locus" of the diagnostic (the span underlined with `^^^`).
+    pub is_primary: bool,
+    pub label: Option<String>,
+}
+
+impl CapturedSpan {
+    fn new(
+        span: Span,
+        is_primary: bool,
+        label: Option<String>,
+        source_map: &SourceMap,
+        working_dir: &Path,
+    ) -> Self {
+        let start = source_map.lookup_char_pos(span.lo());
+        let end = source_map.lookup_char_pos(span.hi());
+
+        Self {
+            file_name: source_map
+                .filename_for_diagnostics(&start.file.name)
+                .to_string(),
+            local_path: local_path(&start.file.name, working_dir),
+            byte_start: start.file.original_relative_byte_pos(span.lo()).0,
+            byte_end: start.file.original_relative_byte_pos(span.hi()).0,
+            line_start: start.line,
+            line_end: end.line,
+            column_start: start.col.0 + 1,
+            column_end: end.col.0 + 1,
+            is_primary,
+            label,
+        }
+    }
+}
+
+/// Returns the path on disk of `file_name`, resolved against `working_dir`.
+pub(crate) fn local_path(file_name: &FileName, working_dir: &Path) -> Option<PathBuf> {
+    match file_name {
+        FileName::Real(name) => name.local_path().map(|path| working_dir.join(path)),
+        _ => None,
+    }
+}
+
+/// One part of a [`CapturedSuggestion`]: the code under `span` should be replaced with
+/// `replacement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedSuggestionPart {
+    pub span: CapturedSpan,
+    pub replacement: String,
+}
+
+/// A code suggestion attached to a [`CapturedDiagnostic`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedSuggestion {
+    pub message: String,
+    pub applicability: Applicability,
+    pub parts: Vec<CapturedSuggestionPart>,
+}
+
+/// A diagnostic emitted by the compiler, with all its information resolved so it can be used
+/// after the compiler has been stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedDiagnostic {
+    pub level: DiagnosticLevel,
+    pub message: String,
+    pub code: Option<DiagnosticCode>,
+    /// Primary and secondary spans of the diagnostic.
+    pub spans: Vec<CapturedSpan>,
+    /// Sub-diagnostics (notes, helps, etc) attached to this diagnostic.
+    pub children: Vec<CapturedDiagnostic>,
+    pub suggestions: Vec<CapturedSuggestion>,
+    /// The diagnostic as rustc would have displayed it (without colors).
+    pub rendered: String,
+}
+
+impl CapturedDiagnostic {
+    /// Returns the first primary span of this diagnostic, if any.
+    pub fn primary_span(&self) -> Option<&CapturedSpan> {
+        self.spans.iter().find(|span| span.is_primary)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level.is_error()
+    }
+}
+
+/// A thread-safe buffer in which a diagnostic is rendered.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Output {
+    Stderr(Box<dyn Emitter + DynSend>),
+    Capture(DiagnosticCollector),
+}
+
+/// Emitter which converts diagnostics into [`CapturedDiagnostic`]. Depending on the
+/// [`DiagnosticOutput`], they are either forwarded to the stderr emitter or stored into the
+/// user's [`DiagnosticCollector`]. In both cases, errors are also stored into `errors` so they
+/// can be returned in [`Error`](crate::Error).
+pub(crate) struct CapturingEmitter {
+    source_map: Lrc<SourceMap>,
+    /// Directory the relative paths of the source map are relative to.
+    working_dir: PathBuf,
+    fallback_bundle: LazyFallbackBundle,
+    output: Output,
+    errors: DiagnosticCollector,
+}
+
+impl CapturingEmitter {
+    /// `stderr_emitter` is only called if `diagnostic_output` is [`DiagnosticOutput::Stderr`].
+    pub(crate) fn new<F: FnOnce() -> Box<dyn Emitter + DynSend>>(
+        source_map: Lrc<SourceMap>,
+        working_dir: PathBuf,
+        diagnostic_output: &DiagnosticOutput,
+        errors: DiagnosticCollector,
+        stderr_emitter: F,
+    ) -> Self {
+        let output = match diagnostic_output {
+            DiagnosticOutput::Stderr => Output::Stderr(stderr_emitter()),
+            DiagnosticOutput::Capture(collector) => Output::Capture(collector.clone()),
+            DiagnosticOutput::Sarif(sarif) => Output::Capture(sarif.diagnostics.clone()),
+        };
+        Self {
+            source_map,
+            working_dir,
+            fallback_bundle: rustc_errors::fallback_fluent_bundle(
+                rustc_driver::DEFAULT_LOCALE_RESOURCES.to_vec(),
+                false,
+            ),
+            output,
+            errors,
+        }
+    }
+
+    fn render(&self, diag: &Diagnostic) -> String {
+        let buffer = SharedBuffer::default();
+        EmitterWriter::new(
+            Box::new(termcolor::NoColor::new(buffer.clone())),
+            self.fallback_bundle.clone(),
+        )
+        .sm(Some(Lrc::clone(&self.source_map)))
+        .emit_diagnostic(diag);
+        let output = std::mem::take(&mut *buffer.0.lock().unwrap());
+        String::from_utf8_lossy(&output).into_owned()
+    }
+
+    /// Translates `message`. If the translation fails, the untranslated message (or the Fluent
+    /// identifier) is returned instead of panicking.
+    fn message_text(&self, message: &DiagnosticMessage, args: &FluentArgs<'_>) -> String {
+        match self.translate_message(message, args) {
+            Ok(text) => text.into_owned(),
+            Err(_) => match message {
+                DiagnosticMessage::Str(text) | DiagnosticMessage::Eager(text) => text.to_string(),
+                DiagnosticMessage::FluentIdentifier(identifier, Some(attr)) => {
+                    format!("{}.{}", identifier, attr)
+                }
+                DiagnosticMessage::FluentIdentifier(identifier, None) => identifier.to_string(),
+            },
+        }
+    }
+
+    fn messages_text(
+        &self,
+        messages: &[(DiagnosticMessage, Style)],
+        args: &FluentArgs<'_>,
+    ) -> String {
+        messages
+            .iter()
+            .map(|(message, _)| self.message_text(message, args))
+            .collect()
+    }
+
+    fn convert_spans(&self, multi_span: &MultiSpan, args: &FluentArgs<'_>) -> Vec<CapturedSpan> {
+        multi_span
+            .span_labels()
+            .into_iter()
+            .filter(|span_label| !span_label.span.is_dummy())
+            .map(|span_label| {
+                let label = span_label
+                    .label
+                    .as_ref()
+                    .map(|label| self.message_text(label, args));
+                CapturedSpan::new(
+                    span_label.span,
+                    span_label.is_primary,
+                    label,
+                    &self.source_map,
+                    &self.working_dir,
+                )
+            })
+            .collect()
+    }
+
+    fn convert(&self, diag: &Diagnostic) -> CapturedDiagnostic {
+        let args = to_fluent_args(diag.args());
+        let code = diag.code.as_ref().map(|code| match code {
+            DiagnosticId::Error(code) => DiagnosticCode::Error(code.clone()),
+            DiagnosticId::Lint { name, .. } => DiagnosticCode::Lint(name.clone()),
+        });
+        let children = diag
+            .children
+            .iter()
+            .map(|child| CapturedDiagnostic {
+                level: DiagnosticLevel::from_level(child.level),
+                message: self.messages_text(&child.message, &args),
+                code: None,
+                spans: self.convert_spans(child.render_span.as_ref().unwrap_or(&child.span), &args),
+                children: Vec::new(),
+                suggestions: Vec::new(),
+                rendered: String::new(),
+            })
+            .collect();
+        let suggestions = diag
+            .suggestions
+            .iter()
+            .flatten()
+            .flat_map(|suggestion| {
+                let message = self.message_text(&suggestion.msg, &args);
+                suggestion
+                    .substitutions
+                    .iter()
+                    .map(move |substitution| CapturedSuggestion {
+                        message: message.clone(),
+                        applicability: suggestion.applicability,
+                        parts: substitution
+                            .parts
+                            .iter()
+                            .map(|part| CapturedSuggestionPart {
+                                span: CapturedSpan::new(
+                                    part.span,
+                                    true,
+                                    None,
+                                    &self.source_map,
+                                    &self.working_dir,
+                                ),
+                                replacement: part.snippet.clone(),
+                            })
+                            .collect(),
+                    })
+            })
+            .collect();
+
+        CapturedDiagnostic {
+            level: DiagnosticLevel::from_level(diag.level()),
+            message: self.messages_text(&diag.message, &args),
+            code,
+            spans: self.convert_spans(&diag.span, &args),
+            children,
+            suggestions,
+            rendered: self.render(diag),
+        }
+    }
+}
+
+impl Translate for CapturingEmitter {
+    fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
+        None
+    }
+
+    fn fallback_fluent_bundle(&self) -> &FluentBundle {
+        &self.fallback_bundle
+    }
+}
+
+impl Emitter for CapturingEmitter {
+    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
+        Some(&self.source_map)
+    }
+
+    fn emit_diagnostic(&mut self, diag: &Diagnostic) {
+        if let Output::Stderr(emitter) = &mut self.output {
+            emitter.emit_diagnostic(diag);
+            if diag.is_error() {
+                self.errors.push(self.convert(diag));
+            }
+        } else if let Output::Capture(collector) = &self.output {
+            let diagnostic = self.convert(diag);
+            if diagnostic.is_error() {
+                self.errors.push(diagnostic.clone());
+            }
+            collector.push(diagnostic);
+        }
+    }
+
+    fn emit_artifact_notification(&mut self, path: &Path, artifact_type: &str) {
+        if let Output::Stderr(emitter) = &mut self.output {
+            emitter.emit_artifact_notification(path, artifact_type);
+        }
+    }
+
+    fn emit_future_breakage_report(&mut self, diags: Vec<Diagnostic>) {
+        if let Output::Stderr(emitter) = &mut self.output {
+            emitter.emit_future_breakage_report(diags);
+        }
+    }
+
+    fn emit_unused_externs(&mut self, lint_level: LintLevel, unused_externs: &[&str]) {
+        if let Output::Stderr(emitter) = &mut self.output {
+            emitter.emit_unused_externs(lint_level, unused_externs);
+        }
+    }
+
+    fn should_show_explain(&self) -> bool {
+        match &self.output {
+            Output::Stderr(emitter) => emitter.should_show_explain(),
+            Output::Capture(_) => true,
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use rustc_span::source_map::SourceMap;
+use rustc_span::{FileName, RealFileName, SourceFile, Span};
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::{local_path, CapturedSuggestion};
+
+/// Number of unchanged lines displayed around the changes in [`SourceEdits::diff`].
+const DIFF_CONTEXT: usize = 3;
+
+/// Replacement of a byte range of a file, stored in [`SourceEdits`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileEdit {
+    /// Byte range of the replaced code, relative to the start of the file. It is empty for an
+    /// insertion.
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// Reason why [`SourceEdits::replace`] (or [`SourceEdits::add_suggestion`]) didn't add an edit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditError {
+    /// The span comes from a macro expansion, so it doesn't point to the code to edit.
+    FromExpansion,
+    /// The source code of the file of the span isn't available (the file comes from another
+    /// crate for example, or the file on disk doesn't match the suggestion anymore).
+    NoSource,
+    /// The span overlaps the byte range of an edit which has already been added.
+    Overlap(Range<usize>),
+}
+
+/// Source code of a file (as it was before rustc normalized it) and the edits to apply to it.
+#[derive(Clone, Debug)]
+struct EditedFile {
+    /// Path of the file on disk, resolved against the working directory of the compiler.
+    path: Option<PathBuf>,
+    source: String,
+    /// Sorted by range, they never overlap.
+    edits: Vec<FileEdit>,
+}
+
+impl EditedFile {
+    /// Uses the source code loaded by the compiler, so it works for the virtual files and for the
+    /// code provided as a string too.
+    fn new(source_file: &SourceFile, path: Option<PathBuf>) -> Result<Self, EditError> {
+        let Some(src) = &source_file.src else {
+            return Err(EditError::NoSource);
+        };
+        // rustc removes the BOM and the `\r` of the `\r\n` line endings, they're put back so the
+        // rest of the file is kept as is.
+        let mut source = String::with_capacity(src.len() + 3 * source_file.normalized_pos.len());
+        let mut last_end = 0;
+        for normalized_pos in &source_file.normalized_pos {
+            match normalized_pos.pos.0 as usize {
+                0 => source.push('\u{feff}'),
+                // The position is the one following the `\n`.
+                pos => {
+                    source.push_str(&src[last_end..pos - 1]);
+                    source.push('\r');
+                    last_end = pos - 1;
+                }
+            }
+        }
+        source.push_str(&src[last_end..]);
+        Ok(Self {
+            path,
+            source,
+            edits: Vec::new(),
+        })
+    }
+
+    fn from_path(path: PathBuf) -> Option<Self> {
+        Some(Self {
+            source: fs::read_to_string(&path).ok()?,
+            path: Some(path),
+            edits: Vec::new(),
+        })
+    }
+
+    /// Adds `edit`, unless the same edit has already been added.
+    fn insert(&mut self, edit: FileEdit) -> Result<(), EditError> {
+        if self.edits.contains(&edit) {
+            return Ok(());
+        }
+        if let Some(other) = self
+            .edits
+            .iter()
+            .find(|other| overlap(&other.range, &edit.range))
+        {
+            return Err(EditError::Overlap(other.range.clone()));
+        }
+        // Insertions go before the replacements starting at the same position.
+        let index = self.edits.partition_point(|other| {
+            (other.range.start, other.range.end) <= (edit.range.start, edit.range.end)
+        });
+        self.edits.insert(index, edit);
+        Ok(())
+    }
+
+    fn apply(&self) -> String {
+        let mut output = String::with_capacity(self.source.len());
+        let mut last_end = 0;
+        for edit in &self.edits {
+            output.push_str(&self.source[last_end..edit.range.start]);
+            output.push_str(&edit.replacement);
+            last_end = edit.range.end;
+        }
+        output.push_str(&self.source[last_end..]);
+        output
+    }
+
+    /// Writes the unified diff hunks of the edits of this file into `output`.
+    fn write_hunks(&self, output: &mut String) {
+        let lines = self.source.split_inclusive('\n').collect::<Vec<_>>();
+        let mut line_starts = Vec::with_capacity(lines.len() + 1);
+        let mut pos = 0;
+        for line in &lines {
+            line_starts.push(pos);
+            pos += line.len();
+        }
+        line_starts.push(pos);
+        // Index of the line containing the byte at `pos` (the last line for the end of the file).
+        let line_of = |pos: usize| {
+            line_starts
+                .partition_point(|start| *start <= pos)
+                .saturating_sub(1)
+                .min(lines.len().saturating_sub(1))
+        };
+
+        // Edits changing the same lines, with the range of these lines.
+        let mut chunks: Vec<(Range<usize>, &[FileEdit])> = Vec::new();
+        let mut chunk_start = 0;
+        for (index, edit) in self.edits.iter().enumerate() {
+            let first = line_of(edit.range.start);
+            let last = line_of(edit.range.end.saturating_sub(1).max(edit.range.start));
+            match chunks.last_mut() {
+                Some((changed, edits)) if first < changed.end => {
+                    changed.end = changed.end.max(last + 1);
+                    *edits = &self.edits[chunk_start..=index];
+                }
+                _ => {
+                    chunk_start = index;
+                    chunks.push((first..last + 1, &self.edits[index..=index]));
+                }
+            }
+        }
+        // Chunks which are close enough to be displayed in the same hunk.
+        let mut hunks: Vec<Vec<(Range<usize>, Vec<String>)>> = Vec::new();
+        for (changed, edits) in chunks {
+            let changed = changed.start.min(lines.len())..changed.end.min(lines.len());
+            let end = line_starts[changed.end];
+            let mut new = String::new();
+            let mut last_end = line_starts[changed.start];
+            for edit in edits {
+                new.push_str(&self.source[last_end..edit.range.start]);
+                new.push_str(&edit.replacement);
+                last_end = edit.range.end;
+            }
+            new.push_str(&self.source[last_end..end]);
+            let new_lines = new.split_inclusive('\n').map(str::to_owned).collect();
+            let chunk = (changed, new_lines);
+            match hunks.last_mut() {
+                Some(hunk) if chunk.0.start <= hunk.last().unwrap().0.end + 2 * DIFF_CONTEXT => {
+                    hunk.push(chunk)
+                }
+                _ => hunks.push(vec![chunk]),
+            }
+        }
+
+        let write_lines = |output: &mut String, prefix: char, lines: &[&str]| {
+            for line in lines {
+                output.push(prefix);
+                output.push_str(line);
+                if !line.ends_with('\n') {
+                    output.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        };
+        // Difference between the line numbers of the new and the old file.
+        let mut offset = 0isize;
+        for hunk in hunks {
+            let first = hunk[0].0.start.saturating_sub(DIFF_CONTEXT);
+            let last = (hunk.last().unwrap().0.end + DIFF_CONTEXT).min(lines.len());
+            let added = hunk
+                .iter()
+                .map(|(changed, new_lines)| new_lines.len() as isize - changed.len() as isize)
+                .sum::<isize>();
+            let old_start = first + 1;
+            let new_start = (old_start as isize + offset) as usize;
+            let old_len = last - first;
+            let new_len = (old_len as isize + added) as usize;
+            writeln!(
+                output,
+                "@@ -{old_start},{old_len} +{new_start},{new_len} @@"
+            )
+            .unwrap();
+
+            let mut context_start = first;
+            for (changed, new_lines) in &hunk {
+                write_lines(output, ' ', &lines[context_start..changed.start]);
+                write_lines(output, '-', &lines[changed.clone()]);
+                let new_lines = new_lines.iter().map(String::as_str).collect::<Vec<&str>>();
+                write_lines(output, '+', &new_lines);
+                context_start = changed.end;
+            }
+            write_lines(output, ' ', &lines[context_start..last]);
+            offset += added;
+        }
+    }
+}
+
+/// Empty ranges only overlap the ranges which strictly contain them.
+fn overlap(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// Text edits collected from `Span`s (provided by any entry point of this crate), which can then
+/// be applied to the files on disk or returned as the new content of the files.
+///
+/// The spans are resolved with the `SourceMap` when the edits are added, so the `SourceEdits` can
+/// be returned from the callback and applied after the compiler is done.
+#[derive(Clone, Debug, Default)]
+pub struct SourceEdits {
+    files: BTreeMap<FileName, EditedFile>,
+}
+
+impl SourceEdits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the code at `span` with `replacement`. Use an empty span to insert code
+    /// (`span.shrink_to_lo()` to insert before a node for example), and an empty `replacement` to
+    /// remove code.
+    ///
+    /// The spans coming from macro expansions are skipped (the arguments of a macro call aren't
+    /// considered as coming from an expansion). Adding the same edit twice isn't an error, the
+    /// edit is only applied once.
+    pub fn replace<S: Into<String>>(
+        &mut self,
+        source_map: &SourceMap,
+        span: Span,
+        replacement: S,
+    ) -> Result<(), EditError> {
+        if span.from_expansion() {
+            return Err(EditError::FromExpansion);
+        }
+        // `span.is_dummy()` cannot be used: an insertion at the start of the first file is empty
+        // at position 0 too.
+        if source_map.files().is_empty() {
+            return Err(EditError::NoSource);
+        }
+        let span = span.data();
+        let source_file = source_map.lookup_source_file(span.lo);
+
+        let file = match self.files.entry(source_file.name.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // The compiler and the parser resolve the relative paths against the current
+                // directory, and the callbacks run while they're running.
+                let working_dir = std::env::current_dir().unwrap_or_default();
+                let path = local_path(&source_file.name, &working_dir);
+                entry.insert(EditedFile::new(&source_file, path)?)
+            }
+        };
+        let offset = |pos| source_file.original_relative_byte_pos(pos).0 as usize;
+        file.insert(FileEdit {
+            range: offset(span.lo)..offset(span.hi),
+            replacement: replacement.into(),
+        })
+    }
+
+    /// Adds the edits of a suggestion captured from a diagnostic (take a look at
+    /// [`DiagnosticOutput::Capture`](crate::DiagnosticOutput::Capture)). The files are read from
+    /// the disk, relatively to the working directory of the compiler which emitted the
+    /// suggestion. Either all the parts of the suggestion are added or none of them.
+    pub fn add_suggestion(&mut self, suggestion: &CapturedSuggestion) -> Result<(), EditError> {
+        let mut added = Vec::with_capacity(suggestion.parts.len());
+        for part in &suggestion.parts {
+            let edit = FileEdit {
+                range: part.span.byte_start as usize..part.span.byte_end as usize,
+                replacement: part.replacement.clone(),
+            };
+            // Same file name as the one of the `SourceFile`, so the edits added with `replace` and
+            // the ones of the suggestions are in the same file.
+            let file_name =
+                FileName::Real(RealFileName::LocalPath(PathBuf::from(&part.span.file_name)));
+            let result = match self.files.get_mut(&file_name) {
+                Some(file) => Ok(file),
+                None => match part.span.local_path.clone().and_then(EditedFile::from_path) {
+                    Some(file) => Ok(self.files.entry(file_name.clone()).or_insert(file)),
+                    None => Err(EditError::NoSource),
+                },
+            }
+            .and_then(|file| {
+                let in_source = edit.range.start <= edit.range.end
+                    && file.source.get(edit.range.clone()).is_some();
+                if !in_source {
+                    return Err(EditError::NoSource);
+                }
+                let is_new = !file.edits.contains(&edit);
+                file.insert(edit.clone())?;
+                Ok(is_new)
+            });
+            match result {
+                Ok(true) => added.push((file_name, edit)),
+                Ok(false) => {}
+                Err(error) => {
+                    for (file_name, edit) in added {
+                        if let Some(file) = self.files.get_mut(&file_name) {
+                            file.edits.retain(|other| *other != edit);
+                        }
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.values().all(|file| file.edits.is_empty())
+    }
+
+    /// Returns the edits of each file, sorted by range.
+    pub fn edits(&self) -> impl Iterator<Item = (&FileName, &[FileEdit])> {
+        self.files
+            .iter()
+            .map(|(file_name, file)| (file_name, file.edits.as_slice()))
+    }
+
+    /// Returns the new content of each edited file.
+    pub fn apply(&self) -> BTreeMap<FileName, String> {
+        self.files
+            .iter()
+            .map(|(file_name, file)| (file_name.clone(), file.apply()))
+            .collect()
+    }
+
+    /// Returns the changes as a unified diff (like `diff -u` or `git diff` output), without
+    /// applying them. It can be used to implement a "dry run" mode.
+    pub fn diff(&self) -> String {
+        let mut output = String::new();
+        for (file_name, file) in &self.files {
+            if file.edits.is_empty() {
+                continue;
+            }
+            let path = file_name.prefer_local();
+            writeln!(output, "--- {path}\n+++ {path}").unwrap();
+            file.write_hunks(&mut output);
+        }
+        output
+    }
+
+    /// Writes the new content of each edited file on disk. Fails if one of the files isn't a file
+    /// on disk (if the code has been provided as a string for example).
+    ///
+    /// Nothing is written if one of the files can't be written: the new contents are first
+    /// written to temporary files next to the edited ones, which then replace them.
+    pub fn write(&self) -> io::Result<()> {
+        let mut outputs = Vec::with_capacity(self.files.len());
+        for (file_name, file) in &self.files {
+            let Some(path) = &file.path else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} isn't a file on disk", file_name.prefer_local()),
+                ));
+            };
+            outputs.push((path, file.apply()));
+        }
+
+        let mut temp_paths = Vec::with_capacity(outputs.len());
+        for (path, output) in &outputs {
+            let temp_path = temp_path(path);
+            let result = write_temp(path, &temp_path, output);
+            temp_paths.push(temp_path);
+            if let Err(error) = result {
+                for temp_path in &temp_paths {
+                    let _ = fs::remove_file(temp_path);
+                }
+                return Err(error);
+            }
+        }
+        for ((path, _), temp_path) in outputs.iter().zip(&temp_paths) {
+            fs::rename(temp_path, path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Path of the temporary file used to write `path`, in the same directory so it can be renamed.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = std::ffi::OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(".rustc-tools.tmp");
+    path.with_file_name(file_name)
+}
+
+/// Writes `output` into `temp_path`, with the permissions of `path`.
+fn write_temp(path: &Path, temp_path: &Path, output: &str) -> io::Result<()> {
+    let permissions = fs::metadata(path)?.permissions();
+    fs::write(temp_path, output)?;
+    fs::set_permissions(temp_path, permissions)
+}
@@ -0,0 +1,92 @@
+use std::any::Any;
+use std::convert::Infallible;
+use std::fmt;
+
+use crate::diagnostics::CapturedDiagnostic;
+
+/// Error returned by the API.
+///
+/// `E` is the error type returned by the callback provided to the entry points. If the callback
+/// returns an error, it'll be returned in `Error::Other`.
+#[derive(Debug)]
+pub enum Error<E = Infallible> {
+    /// The parser failed. It contains the errors it emitted.
+    Parser(Vec<CapturedDiagnostic>),
+    /// The compiler (or the callback) panicked. It contains the panic payload, which can be
+    /// passed to [`std::panic::resume_unwind`] to continue unwinding. Its message is returned by
+    /// [`Error::panic_message`].
+    Panic(Box<dyn Any + Send>),
+    /// The arguments provided to the compiler are invalid.
+    InvalidArguments(String),
+    /// The compilation failed. It contains the errors emitted by the compiler.
+    Compilation(Vec<CapturedDiagnostic>),
+    /// Running cargo failed (only returned by [`CargoDriver`](crate::CargoDriver)).
+    Cargo(String),
+    /// Error returned by the callback.
+    Other(E),
+}
+
+impl<E> Error<E> {
+    /// Returns the message of the panic if this is an [`Error::Panic`] whose payload is a string
+    /// (which is the case for `panic!` and the compiler bugs).
+    pub fn panic_message(&self) -> Option<&str> {
+        match self {
+            Self::Panic(payload) => panic_message(payload.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Returns the diagnostics contained in this error (if any).
+    pub fn diagnostics(&self) -> &[CapturedDiagnostic] {
+        match self {
+            Self::Parser(diagnostics) | Self::Compilation(diagnostics) => diagnostics,
+            _ => &[],
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        Some(s)
+    } else {
+        payload.downcast_ref::<String>().map(String::as_str)
+    }
+}
+
+fn fmt_diagnostics(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    diagnostics: &[CapturedDiagnostic],
+) -> fmt::Result {
+    write!(f, "{}", title)?;
+    for diagnostic in diagnostics {
+        write!(f, "\n{}", diagnostic.rendered.trim_end())?;
+    }
+    Ok(())
+}
+
+impl<E: fmt::Display> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parser(diagnostics) => fmt_diagnostics(f, "failed to parse", diagnostics),
+            Self::Panic(payload) => write!(
+                f,
+                "the compiler panicked: {}",
+                panic_message(payload.as_ref()).unwrap_or("Box<dyn Any>"),
+            ),
+            Self::InvalidArguments(message) => write!(f, "invalid arguments: {}", message),
+            Self::Compilation(diagnostics) => fmt_diagnostics(f, "compilation failed", diagnostics),
+            Self::Cargo(message) => write!(f, "cargo failed: {}", message),
+            Self::Other(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Other(error) => Some(error),
+            _ => None,
+        }
+    }
+}
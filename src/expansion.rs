@@ -0,0 +1,76 @@
+use rustc_ast::ast::Crate;
+use rustc_middle::ty::{ResolverAstLowering, TyCtxt};
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+use rustc_span::{Span, Symbol};
+
+use std::marker;
+
+use crate::config::AnalysisConfig;
+use crate::hir::run_with_config;
+use crate::Error;
+
+/// If you need the AST once the macros have been expanded and the names resolved, this is the
+/// function you will use. Unlike [`with_ast_parser`](crate::with_ast_parser), the whole crate is
+/// loaded (`mod` declarations are followed), the `cfg` attributes are applied and the macros are
+/// expanded.
+///
+/// The `callback` gets the expanded [`Crate`] and the [`ResolverAstLowering`] which contains the
+/// name resolution results (like `partial_res_map` which gives what a path resolves to from its
+/// `NodeId`). The other resolver outputs can be accessed with `tcx.resolutions(())`. To know which
+/// macro generated a node, use [`macro_call_site`] on its span.
+///
+/// **VERY IMPORTANT TO NOTE**: the HIR is created from the expanded AST, so it cannot be used in
+/// the `callback` (the compiler would panic and [`Error::Panic`] would be returned).
+///
+/// The `level` of `config` is ignored. If the `callback` returns an error, it is returned as
+/// [`Error::Other`].
+///
+/// [`Crate`]: https://doc.rust-lang.org/nightly/nightly-rustc/rustc_ast/ast/struct.Crate.html
+/// [`ResolverAstLowering`]: https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.ResolverAstLowering.html
+pub fn with_expanded_ast<
+    T: marker::Send,
+    E: marker::Send,
+    F: FnOnce(TyCtxt<'_>, &Crate, &ResolverAstLowering) -> Result<T, E> + marker::Send,
+>(
+    config: &AnalysisConfig,
+    callback: F,
+) -> Result<T, Error<E>> {
+    run_with_config(config, |tcx| {
+        let resolver_for_lowering = tcx.resolver_for_lowering(());
+        if tcx.sess.diagnostic().has_errors_or_lint_errors().is_some() {
+            rustc_errors::FatalError.raise();
+        }
+        let resolver_and_crate = resolver_for_lowering.borrow();
+        let (resolver, krate) = &*resolver_and_crate;
+
+        callback(tcx, krate, resolver).map_err(Error::Other)
+    })
+}
+
+/// Macro call from which code has been generated, returned by [`macro_call_site`].
+#[derive(Clone, Copy, Debug)]
+pub struct MacroCall {
+    /// Span of the macro call in the source code (`println!("a")`, the item with the
+    /// `#[derive(Debug)]` attribute, etc).
+    pub span: Span,
+    /// Name of the macro (`println`, `Debug`, etc).
+    pub name: Symbol,
+    /// Kind of the macro (bang, attribute or derive).
+    pub kind: MacroKind,
+}
+
+/// Returns the outermost macro call which generated the code at `span`, so the expanded code can
+/// be mapped back to the source code. Returns `None` if `span` doesn't come from a macro
+/// expansion (desugarings like `for` loops or `?` aren't considered as macros).
+pub fn macro_call_site(span: Span) -> Option<MacroCall> {
+    span.macro_backtrace()
+        .filter_map(|expn_data| match expn_data.kind {
+            ExpnKind::Macro(kind, name) => Some(MacroCall {
+                span: expn_data.call_site,
+                name,
+                kind,
+            }),
+            _ => None,
+        })
+        .last()
+}
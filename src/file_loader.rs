@@ -0,0 +1,52 @@
+use rustc_data_structures::sync::Lrc;
+use rustc_span::source_map::{FileLoader, RealFileLoader};
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Files provided with [`AnalysisConfig::virtual_file`](crate::AnalysisConfig::virtual_file).
+pub(crate) type VirtualFiles = Arc<BTreeMap<PathBuf, String>>;
+
+/// Removes the `.` components so `./src/lib.rs` and `src/lib.rs` are the same file.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// Reads the files from the virtual files first, and from the disk if they're not in it.
+pub(crate) struct VirtualFileLoader {
+    files: VirtualFiles,
+}
+
+impl VirtualFileLoader {
+    pub(crate) fn new(files: VirtualFiles) -> Self {
+        Self { files }
+    }
+
+    fn get(&self, path: &Path) -> Option<&String> {
+        self.files.get(&normalize(path))
+    }
+}
+
+impl FileLoader for VirtualFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        self.get(path).is_some() || RealFileLoader.file_exists(path)
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        match self.get(path) {
+            Some(content) => Ok(content.clone()),
+            None => RealFileLoader.read_file(path),
+        }
+    }
+
+    fn read_binary_file(&self, path: &Path) -> io::Result<Lrc<[u8]>> {
+        match self.get(path) {
+            Some(content) => Ok(content.as_bytes().into()),
+            None => RealFileLoader.read_binary_file(path),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use rustc_errors::Applicability;
+use rustc_lint::LintStore;
+
+use crate::config::AnalysisConfig;
+use crate::diagnostics::{CapturedDiagnostic, DiagnosticCollector, DiagnosticOutput};
+use crate::edits::SourceEdits;
+use crate::lint::with_lints_config;
+use crate::Error;
+
+/// Suggestions collected by [`with_lints_fix`].
+#[derive(Clone, Debug, Default)]
+pub struct LintFixes {
+    /// The edits of the accepted suggestions. Call [`SourceEdits::write`] to rewrite the source
+    /// files, or [`SourceEdits::diff`] to display the changes without applying them (dry run).
+    pub edits: SourceEdits,
+    /// The diagnostics whose suggestions have been added to `edits`.
+    pub fixed: Vec<CapturedDiagnostic>,
+    /// The other diagnostics. Their `rendered` field can be printed to display them like rustc.
+    pub unfixed: Vec<CapturedDiagnostic>,
+}
+
+/// Runs the lints registered in `callback` (like [`with_lints_config`]) and collects the
+/// suggestions of the emitted diagnostics whose applicability is in `applicabilities` (usually
+/// only [`Applicability::MachineApplicable`]), like `cargo fix` does.
+///
+/// If a diagnostic has multiple suggestions, the first one which doesn't overlap an already
+/// accepted suggestion is used. Suggestions on files which aren't on disk are skipped.
+///
+/// The diagnostics can only be sorted once the compiler is done, so they're never printed: the
+/// ones which haven't been fixed are returned in [`LintFixes::unfixed`]. If the
+/// [`DiagnosticOutput`] of `config` is [`DiagnosticOutput::Capture`] or
+/// [`DiagnosticOutput::Sarif`], all of them are sent to it too. Just like `cargo fix`, nothing is
+/// fixed if the compilation fails: deny-level lints make the compilation fail, so use warn-level
+/// lints to fix them.
+pub fn with_lints_fix<F: Fn(&mut LintStore) + Send + Sync + 'static>(
+    config: &AnalysisConfig,
+    applicabilities: &[Applicability],
+    callback: F,
+) -> Result<LintFixes, Error> {
+    let collector = DiagnosticCollector::new();
+    let capture_output = match &config.diagnostic_output {
+        // The lints metadata still needs to be stored.
+        DiagnosticOutput::Sarif(sarif) => {
+            DiagnosticOutput::Sarif(sarif.with_diagnostics(collector.clone()))
+        }
+        _ => DiagnosticOutput::Capture(collector.clone()),
+    };
+    let capture_config = config.clone().diagnostic_output(capture_output);
+    let result = with_lints_config(&capture_config, callback);
+    let diagnostics = collector.take();
+
+    let mut fixes = LintFixes::default();
+    for diagnostic in diagnostics {
+        let is_fixed = result.is_ok()
+            && diagnostic
+                .suggestions
+                .iter()
+                .filter(|suggestion| applicabilities.contains(&suggestion.applicability))
+                .any(|suggestion| fixes.edits.add_suggestion(suggestion).is_ok());
+        match &config.diagnostic_output {
+            DiagnosticOutput::Capture(output) => output.push(diagnostic.clone()),
+            DiagnosticOutput::Sarif(sarif) => sarif.diagnostics.push(diagnostic.clone()),
+            DiagnosticOutput::Stderr => {}
+        }
+        if is_fixed {
+            fixes.fixed.push(diagnostic);
+        } else {
+            fixes.unfixed.push(diagnostic);
+        }
+    }
+    result.map(|()| fixes)
+}
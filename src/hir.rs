@@ -1,32 +1,41 @@
-use rustc_data_structures::marker::DynSend;
 use rustc_data_structures::sync::Lrc;
-use rustc_data_structures::unord::UnordSet;
 use rustc_driver::abort_on_err;
-use rustc_errors::emitter::{Emitter, EmitterWriter};
+use rustc_errors::emitter::EmitterWriter;
 use rustc_errors::json::JsonEmitter;
 use rustc_errors::ErrorGuaranteed;
 use rustc_feature::UnstableFeatures;
-use rustc_hir::def_id::LocalDefId;
+use rustc_hir::def::DefKind;
 use rustc_interface::interface;
+use rustc_lint::LintStore;
+use rustc_lint_defs::Level;
 use rustc_middle::ty::TyCtxt;
 use rustc_session::config::{
     parse_crate_types_from_list, parse_externs, rustc_optgroups, CodegenOptions, ErrorOutputType,
     Input, Options, UnstableOptions,
 };
+use rustc_session::parse::ParseSess;
 use rustc_session::search_paths::SearchPath;
-use rustc_session::{config, getopts, EarlyErrorHandler};
+use rustc_session::{config, getopts, EarlyErrorHandler, Session};
 use rustc_span::source_map::{FilePathMapping, SourceMap};
 use rustc_span::FileName;
 
 use std::io::{self, Read};
 use std::marker;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::PathBuf;
-use std::sync::LazyLock;
+use std::sync::Once;
+
+use crate::config::{AnalysisConfig, AnalysisLevel};
+use crate::diagnostics::{CapturingEmitter, DiagnosticCollector, DiagnosticOutput};
+use crate::queries::{override_queries, QueryOverridesGuard};
+use crate::Error;
 
 /// If you need more information than what is provided by
 /// [`with_ast_parser`](crate::with_ast_parser), this is the function you will use.
 ///
 /// * `rustc_args` argument is what will be provided to rustc.
+/// * `diagnostic_output` argument allows you to choose if the diagnostics should be printed on
+///   stderr or captured (take a look at [`DiagnosticOutput`] for more information).
 /// * `callback` argument is the callback that will be called once everything has been set up. It
 ///   provides a [`TyCtxt`] instance which will allow to use the rustc query engine.
 ///
@@ -43,41 +52,133 @@ use std::sync::LazyLock;
 /// the [HIR visitor](https://doc.rust-lang.org/nightly/nightly-rustc/rustc_hir/intravisit/trait.Visitor.html).
 /// (You can take a look at how to use it with `examples/hir.rs`.)
 ///
+/// If the `callback` returns an error, it is returned as [`Error::Other`].
+///
+/// If you don't want to write the rustc arguments yourself, take a look at
+/// [`with_tyctxt_config`].
+///
 /// [`TyCtxt`]: https://doc.rust-lang.org/nightly/nightly-rustc/rustc_middle/ty/struct.TyCtxt.html
-pub fn with_tyctxt<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
+pub fn with_tyctxt<
+    T: marker::Send,
+    E: marker::Send,
+    F: FnOnce(TyCtxt<'_>) -> Result<T, E> + marker::Send,
+>(
     rustc_args: &[String],
+    diagnostic_output: DiagnosticOutput,
+    callback: F,
+) -> Result<T, Error<E>> {
+    let config = AnalysisConfig::from_args(rustc_args).diagnostic_output(diagnostic_output);
+    with_tyctxt_config(&config, callback)
+}
+
+/// Same as [`with_tyctxt`], but the compiler is configured with an [`AnalysisConfig`] instead of
+/// command line arguments.
+pub fn with_tyctxt_config<
+    T: marker::Send,
+    E: marker::Send,
+    F: FnOnce(TyCtxt<'_>) -> Result<T, E> + marker::Send,
+>(
+    config: &AnalysisConfig,
     callback: F,
-) -> Result<T, String> {
+) -> Result<T, Error<E>> {
+    let level = config.level;
+    run_with_config(config, |tcx| {
+        run_passes(tcx, level);
+        if tcx.sess.diagnostic().has_errors_or_lint_errors().is_some() {
+            rustc_errors::FatalError.raise();
+        }
+
+        callback(tcx).map_err(Error::Other)
+    })
+}
+
+/// Runs the compiler configured with `config` and calls `callback` once the global context is
+/// created. The compiler errors and panics are converted into [`Error`].
+pub(crate) fn run_with_config<
+    T: marker::Send,
+    E: marker::Send,
+    F: FnOnce(TyCtxt<'_>) -> Result<T, Error<E>> + marker::Send,
+>(
+    config: &AnalysisConfig,
+    callback: F,
+) -> Result<T, Error<E>> {
+    // Errors are always stored so they can be returned in `Error`.
+    let errors = DiagnosticCollector::new();
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let config = match rustc_driver::catch_fatal_errors(|| {
+            create_config_from_args(config, errors.clone())
+        }) {
+            Ok(Ok(config)) => config,
+            Ok(Err(error)) => return Err(error),
+            Err(_) => {
+                return Err(Error::InvalidArguments(
+                    "failed to parse arguments".to_owned(),
+                ))
+            }
+        };
+
+        rustc_driver::catch_fatal_errors(|| run_compiler(config, callback))
+            .unwrap_or_else(|_| Err(Error::Compilation(errors.take())))
+    }))
+    .unwrap_or_else(|payload| Err(Error::Panic(payload)))
+}
+
+fn create_config_from_args<E>(
+    analysis_config: &AnalysisConfig,
+    errors: DiagnosticCollector,
+) -> Result<interface::Config, Error<E>> {
     let mut handler = EarlyErrorHandler::new(ErrorOutputType::default());
     // Most of this code comes from rustdoc.
-    rustc_driver::init_rustc_env_logger(&handler);
-    let args = rustc_driver::args::arg_expand_all(&handler, rustc_args);
+    init_logger(&handler);
+    let args = rustc_driver::args::arg_expand_all(&handler, &analysis_config.to_args());
 
     let mut options = getopts::Options::new();
     for option in rustc_optgroups() {
         (option.apply)(&mut options);
     }
-    let matches = match options.parse(&args[..]) {
-        Ok(m) => m,
-        Err(err) => {
-            handler.early_error(err.to_string());
-        }
-    };
+    let matches = options
+        .parse(&args[..])
+        .map_err(|err| Error::InvalidArguments(err.to_string()))?;
 
     // Note that we discard any distinction between different non-zero exit
     // codes from `from_matches` here.
-    let config = match create_config(&mut handler, &matches, args) {
-        Some(opts) => opts,
-        None => return Err("Failed to create_config".to_owned()),
-    };
+    match create_config(
+        &mut handler,
+        &matches,
+        args,
+        analysis_config,
+        errors.clone(),
+    ) {
+        Some(config) => Ok(config),
+        None => Err(Error::InvalidArguments(
+            errors
+                .take()
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )),
+    }
+}
 
+fn run_compiler<
+    T: marker::Send,
+    E: marker::Send,
+    F: FnOnce(TyCtxt<'_>) -> Result<T, Error<E>> + marker::Send,
+>(
+    config: interface::Config,
+    callback: F,
+) -> Result<T, Error<E>> {
     interface::run_compiler(config, |compiler| {
+        // The overrides were installed in this thread when the session was created.
+        let _query_overrides = QueryOverridesGuard;
         let sess = compiler.session();
 
         if sess.opts.describe_lints {
-            sess.diagnostic()
-                .err("`describe-lints` option is not allowed");
-            return Err("`describe-lints` option is not allowed".to_owned());
+            return Err(Error::InvalidArguments(
+                "`describe-lints` option is not allowed".to_owned(),
+            ));
         }
 
         compiler.enter(|queries| {
@@ -87,31 +188,49 @@ pub fn with_tyctxt<T: marker::Send, F: FnOnce(TyCtxt<'_>) -> T + marker::Send>(
 
             let mut global_ctxt = abort_on_err(queries.global_ctxt(), sess);
 
-            global_ctxt.enter(|tcx| {
-                tcx.sess.time("type_collecting", || {
-                    tcx.hir()
-                        .for_each_module(|module| tcx.ensure().collect_mod_item_types(module))
-                });
-                tcx.sess.time("item_types_checking", || {
-                    tcx.hir()
-                        .for_each_module(|module| tcx.ensure().check_mod_item_types(module))
-                });
-                tcx.sess.abort_if_errors();
-                tcx.sess.time("check_mod_attrs", || {
-                    tcx.hir()
-                        .for_each_module(|module| tcx.ensure().check_mod_attrs(module))
-                });
-                rustc_passes::stability::check_unused_or_stable_features(tcx);
-                if tcx.sess.diagnostic().has_errors_or_lint_errors().is_some() {
-                    rustc_errors::FatalError.raise();
-                }
-
-                Ok(callback(tcx))
-            })
+            global_ctxt.enter(callback)
         })
     })
 }
 
+/// Runs the compiler passes required by `level`.
+fn run_passes(tcx: TyCtxt<'_>, level: AnalysisLevel) {
+    if level == AnalysisLevel::Full {
+        abort_on_err(tcx.analysis(()), tcx.sess);
+        return;
+    }
+    // Expands the macros and resolves the names.
+    tcx.ensure().resolver_for_lowering(());
+    if level >= AnalysisLevel::ItemTypes {
+        tcx.sess.time("type_collecting", || {
+            tcx.hir()
+                .for_each_module(|module| tcx.ensure().collect_mod_item_types(module))
+        });
+        tcx.sess.time("item_types_checking", || {
+            tcx.hir()
+                .for_each_module(|module| tcx.ensure().check_mod_item_types(module))
+        });
+        tcx.sess.abort_if_errors();
+    }
+    if level >= AnalysisLevel::Hir {
+        tcx.sess.time("check_mod_attrs", || {
+            tcx.hir()
+                .for_each_module(|module| tcx.ensure().check_mod_attrs(module))
+        });
+        rustc_passes::stability::check_unused_or_stable_features(tcx);
+    }
+    if level >= AnalysisLevel::TypeCheck {
+        tcx.sess.time("type_check_bodies", || {
+            tcx.hir().par_body_owners(|def_id| {
+                // Like rustc, the anonymous constants are type checked with their parent.
+                if tcx.def_kind(def_id) != DefKind::AnonConst {
+                    tcx.ensure().typeck(def_id);
+                }
+            })
+        });
+    }
+}
+
 fn make_input(
     handler: &EarlyErrorHandler,
     free_matches: &[String],
@@ -145,62 +264,135 @@ fn make_input(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn new_handler(
     error_format: ErrorOutputType,
     source_map: Option<Lrc<SourceMap>>,
+    working_dir: PathBuf,
     diagnostic_width: Option<usize>,
     unstable_opts: &UnstableOptions,
+    can_emit_warnings: bool,
+    diagnostic_output: &DiagnosticOutput,
+    errors: DiagnosticCollector,
 ) -> rustc_errors::Handler {
-    let fallback_bundle = rustc_errors::fallback_fluent_bundle(
-        rustc_driver::DEFAULT_LOCALE_RESOURCES.to_vec(),
-        false,
-    );
-    let emitter: Box<dyn Emitter + DynSend> = match error_format {
-        ErrorOutputType::HumanReadable(kind) => {
-            let (short, color_config) = kind.unzip();
-            Box::new(
-                EmitterWriter::stderr(color_config, fallback_bundle)
-                    .sm(source_map.clone())
-                    .short_message(short)
-                    .teach(unstable_opts.teach)
-                    .diagnostic_width(diagnostic_width)
-                    .track_diagnostics(unstable_opts.track_diagnostics)
-                    .ui_testing(unstable_opts.ui_testing),
-            )
-        }
-        ErrorOutputType::Json {
-            pretty,
-            json_rendered,
-        } => {
-            let source_map =
-                source_map.unwrap_or_else(|| Lrc::new(SourceMap::new(FilePathMapping::empty())));
-            Box::new(
-                JsonEmitter::stderr(
-                    None,
-                    source_map,
-                    None,
-                    fallback_bundle,
+    let source_map =
+        source_map.unwrap_or_else(|| Lrc::new(SourceMap::new(FilePathMapping::empty())));
+    let emitter = CapturingEmitter::new(
+        Lrc::clone(&source_map),
+        working_dir,
+        diagnostic_output,
+        errors,
+        || {
+            let fallback_bundle = rustc_errors::fallback_fluent_bundle(
+                rustc_driver::DEFAULT_LOCALE_RESOURCES.to_vec(),
+                false,
+            );
+            match error_format {
+                ErrorOutputType::HumanReadable(kind) => {
+                    let (short, color_config) = kind.unzip();
+                    Box::new(
+                        EmitterWriter::stderr(color_config, fallback_bundle)
+                            .sm(Some(source_map))
+                            .short_message(short)
+                            .teach(unstable_opts.teach)
+                            .diagnostic_width(diagnostic_width)
+                            .track_diagnostics(unstable_opts.track_diagnostics)
+                            .ui_testing(unstable_opts.ui_testing),
+                    )
+                }
+                ErrorOutputType::Json {
                     pretty,
                     json_rendered,
-                    diagnostic_width,
-                    false,
-                    unstable_opts.track_diagnostics,
-                    rustc_errors::TerminalUrl::No,
-                )
-                .ui_testing(unstable_opts.ui_testing),
-            )
-        }
-    };
+                } => Box::new(
+                    JsonEmitter::stderr(
+                        None,
+                        source_map,
+                        None,
+                        fallback_bundle,
+                        pretty,
+                        json_rendered,
+                        diagnostic_width,
+                        false,
+                        unstable_opts.track_diagnostics,
+                        rustc_errors::TerminalUrl::No,
+                    )
+                    .ui_testing(unstable_opts.ui_testing),
+                ),
+            }
+        },
+    );
+
+    rustc_errors::Handler::with_emitter(Box::new(emitter))
+        .with_flags(unstable_opts.diagnostic_handler_flags(can_emit_warnings))
+}
 
-    rustc_errors::Handler::with_emitter(emitter)
-        .with_flags(unstable_opts.diagnostic_handler_flags(true))
+/// Initializes the rustc logger (`RUSTC_LOG`). It can only be done once per process, otherwise
+/// rustc panics.
+pub(crate) fn init_logger(handler: &EarlyErrorHandler) {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| rustc_driver::init_rustc_env_logger(handler));
+}
+
+/// Returns `false` if warnings are disabled, following the same logic as rustc.
+fn can_emit_warnings(opts: &Options) -> bool {
+    let warnings_allow = opts
+        .lint_opts
+        .iter()
+        .rfind(|(key, _)| key == "warnings")
+        .is_some_and(|(_, level)| *level == Level::Allow);
+    let cap_lints_allow = opts.lint_cap.is_some_and(|cap| cap == Level::Allow);
+    !(warnings_allow || cap_lints_allow)
+}
+
+/// Returns a callback which replaces the diagnostic handler created by rustc with one using
+/// `diagnostic_output` and storing the errors into `errors`.
+pub(crate) fn replace_handler(
+    opts: &Options,
+    diagnostic_output: DiagnosticOutput,
+    errors: DiagnosticCollector,
+) -> impl FnOnce(&mut ParseSess) + marker::Send {
+    let error_format = opts.error_format;
+    let diagnostic_width = opts.diagnostic_width;
+    let unstable_opts = opts.unstable_opts.clone();
+    let can_emit_warnings = can_emit_warnings(opts);
+    let working_dir = opts.working_dir.local_path_if_available().to_owned();
+
+    move |parse_sess: &mut ParseSess| {
+        parse_sess.span_diagnostic = new_handler(
+            error_format,
+            Some(parse_sess.clone_source_map()),
+            working_dir.clone(),
+            diagnostic_width,
+            &unstable_opts,
+            can_emit_warnings,
+            &diagnostic_output,
+            errors,
+        );
+    }
+}
+
+/// Stores the metadata of the lints into the [`SarifCollector`](crate::SarifCollector) (if any).
+#[allow(clippy::type_complexity)]
+fn register_sarif_rules(
+    diagnostic_output: &DiagnosticOutput,
+) -> Option<Box<dyn Fn(&Session, &mut LintStore) + Send + Sync>> {
+    let DiagnosticOutput::Sarif(sarif) = diagnostic_output else {
+        return None;
+    };
+    let sarif = sarif.clone();
+    Some(Box::new(move |sess, lint_store| {
+        sarif.register_rules(lint_store, sess.edition())
+    }))
 }
 
 fn create_config(
     handler: &mut EarlyErrorHandler,
     matches: &getopts::Matches,
     args: Vec<String>,
+    analysis_config: &AnalysisConfig,
+    errors: DiagnosticCollector,
 ) -> Option<interface::Config> {
+    let diagnostic_output = analysis_config.diagnostic_output.clone();
     let color = config::parse_color(handler, matches);
     let config::JsonConfig { json_rendered, .. } = config::parse_json(handler, matches);
     let error_format = config::parse_error_format(handler, matches, color, json_rendered);
@@ -209,12 +401,22 @@ fn create_config(
     let codegen_options = CodegenOptions::build(handler, matches);
     let unstable_opts = UnstableOptions::build(handler, matches);
 
-    let diag = new_handler(error_format, None, diagnostic_width, &unstable_opts);
+    let diag = new_handler(
+        error_format,
+        None,
+        // The handler is only used before the session is created, so there are no files yet.
+        PathBuf::new(),
+        diagnostic_width,
+        &unstable_opts,
+        true,
+        &diagnostic_output,
+        errors.clone(),
+    );
 
     let (lint_opts, describe_lints, lint_cap) = config::get_cmd_lint_options(handler, matches);
 
     let input = match make_input(handler, &matches.free, &diag) {
-        Ok(Some(i)) => i,
+        Ok(Some(i)) => analysis_config.input().unwrap_or(i),
         Ok(None) => {
             return None;
         }
@@ -244,7 +446,7 @@ fn create_config(
     };
     let crate_name = matches.opt_str("crate-name");
 
-    let sessopts = config::Options {
+    let mut sessopts = config::Options {
         maybe_sysroot: matches.opt_str("sysroot").map(PathBuf::from),
         search_paths: libs,
         crate_types,
@@ -264,6 +466,12 @@ fn create_config(
         test: false,
         ..Options::default()
     };
+    analysis_config.path_options().apply(&mut sessopts);
+
+    let replace_handler = replace_handler(&sessopts, diagnostic_output, errors);
+    // The bodies aren't type checked below `TypeCheck`, so the queries requiring it are disabled.
+    let query_overrides =
+        analysis_config.query_overrides(analysis_config.level < AnalysisLevel::TypeCheck);
 
     Some(interface::Config {
         opts: sessopts,
@@ -272,19 +480,14 @@ fn create_config(
         input,
         output_file: None,
         output_dir: None,
-        file_loader: None,
+        file_loader: analysis_config.file_loader(),
         lint_caps: Default::default(),
-        parse_sess_created: None,
-        register_lints: None,
-        override_queries: Some(|_sess, providers| {
-            // Most lints will require typechecking, so just don't run them.
-            providers.lint_mod = |_, _| {};
-            // hack so that `used_trait_imports` won't try to call typeck
-            providers.used_trait_imports = |_, _| {
-                static EMPTY_SET: LazyLock<UnordSet<LocalDefId>> = LazyLock::new(UnordSet::default);
-                &EMPTY_SET
-            };
-        }),
+        parse_sess_created: Some(Box::new(move |parse_sess| {
+            replace_handler(parse_sess);
+            query_overrides.install();
+        })),
+        register_lints: register_sarif_rules(&analysis_config.diagnostic_output),
+        override_queries: Some(override_queries),
         make_codegen_backend: None,
         registry: rustc_driver::diagnostics_registry(),
         locale_resources: rustc_driver::DEFAULT_LOCALE_RESOURCES,
@@ -0,0 +1,79 @@
+use std::fmt::{self, Write};
+
+/// The subset of JSON needed to write the SARIF logs and the lints catalogs.
+pub(crate) enum Json {
+    String(String),
+    Number(usize),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_owned())
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(s) => write_json_string(f, s),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Array(values) => {
+                f.write_char('[')?;
+                for (index, value) in values.iter().enumerate() {
+                    if index != 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(fields) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index != 0 {
+                        f.write_char(',')?;
+                    }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping() {
+        let json = Json::Object(vec![
+            ("a\"b", Json::from("\"q\" \\ \n\r\t\u{1}\u{1f}")),
+            ("non_ascii", Json::from("é☃😀")),
+            ("list", Json::Array(vec![Json::Number(1), Json::from("")])),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"a\"b":"\"q\" \\ \n\r\t\u0001\u001f","non_ascii":"é☃😀","list":[1,""]}"#,
+        );
+    }
+}
@@ -4,11 +4,16 @@
 
 // We need to import them like this otherwise it doesn't work.
 extern crate rustc_ast;
+extern crate rustc_ast_pretty;
+extern crate rustc_attr;
 extern crate rustc_data_structures;
 extern crate rustc_driver;
+extern crate rustc_error_messages;
 extern crate rustc_errors;
+extern crate rustc_expand;
 extern crate rustc_feature;
 extern crate rustc_hir;
+extern crate rustc_hir_pretty;
 extern crate rustc_interface;
 extern crate rustc_lexer;
 extern crate rustc_lint;
@@ -18,24 +23,67 @@ extern crate rustc_parse;
 extern crate rustc_passes;
 extern crate rustc_session;
 extern crate rustc_span;
+extern crate termcolor;
+extern crate thin_vec;
 
 mod ast;
+mod cargo;
+mod config;
+mod diagnostics;
+mod edits;
+mod error;
+mod expansion;
+mod file_loader;
+mod fix;
 mod hir;
+mod json;
 mod lint;
+mod lint_config;
+mod literals;
+mod mir;
+mod modules;
+mod pretty;
+mod queries;
+mod recovery;
+mod registry;
+mod sarif;
+pub mod testing;
+mod tokens;
 
-pub use ast::with_ast_parser;
-pub use hir::with_tyctxt;
-pub use lint::with_lints;
+pub use ast::{
+    with_ast_parser, with_ast_parser_config, with_ast_parser_from_source,
+    with_ast_parser_recovering, with_attribute_parser, with_expr_parser, with_item_parser,
+    with_pat_parser, with_stmts_parser, with_ty_parser,
+};
+pub use cargo::CargoDriver;
+pub use config::{AnalysisConfig, AnalysisLevel, ErrorFormat, ParseConfig};
+pub use diagnostics::{
+    CapturedDiagnostic, CapturedSpan, CapturedSuggestion, CapturedSuggestionPart, DiagnosticCode,
+    DiagnosticCollector, DiagnosticLevel, DiagnosticOutput,
+};
+pub use edits::{EditError, FileEdit, SourceEdits};
+pub use error::Error;
+pub use expansion::{macro_call_site, with_expanded_ast, MacroCall};
+pub use fix::{with_lints_fix, LintFixes};
+pub use hir::{with_tyctxt, with_tyctxt_config};
+pub use lint::{with_lints, with_lints_config};
+pub use lint_config::{
+    FromLintOption, LintConfig, LintConfigError, LintConfigExt, LintLevel, LintOption,
+};
+pub use literals::{unescape_literal, Literal, LiteralError, LiteralErrorKind};
+pub use mir::{crate_mir_to_string, local_mir_bodies, mir_to_string};
+pub use pretty::{
+    crate_to_string, crate_to_string_with_comments, expr_to_string, hir_crate_to_string,
+    hir_expr_to_string, hir_item_to_string, hir_ty_to_string, item_to_string, ty_to_string,
+};
+pub use queries::QueryOverride;
+pub use registry::{CatalogFormat, LintInfo, LintRegistry};
+pub use sarif::SarifCollector;
+pub use tokens::{tokens, LineColumn, Token, TokenError, Tokens};
 
-/// Error returned by the API. If the parser encounters a problem, it'll always be `Error::Parser`.
-/// `Error::Other` is for users.
-pub enum Error<E> {
-    Parser(String),
-    Other(E),
-}
-
-/// Very basic lexer which return a lexer iterator. It doesn't handle errors or anything. For more
-/// advanced usage, take a look at [`with_ast_parser`] instead.
+/// Very basic lexer which return a lexer iterator. It doesn't handle errors or anything. If you
+/// need the position and the text of the tokens, take a look at [`tokens()`]. For more advanced
+/// usage, take a look at [`with_ast_parser`] instead.
 pub fn lexer(source_code: &str) -> rustc_lexer::Cursor<'_> {
     rustc_lexer::Cursor::new(source_code)
 }
@@ -3,25 +3,87 @@
 use rustc_driver::Callbacks;
 use rustc_interface::interface::Config;
 use rustc_lint::LintStore;
-use rustc_session::config::ErrorOutputType;
+use rustc_session::config::{ErrorOutputType, Input};
 use rustc_session::EarlyErrorHandler;
-use rustc_span::{ErrorGuaranteed, Symbol};
+use rustc_span::source_map::FileLoader;
+use rustc_span::Symbol;
 
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::Arc;
 
-struct Lints {
+use crate::config::{AnalysisConfig, PathOptions};
+use crate::diagnostics::{DiagnosticCollector, DiagnosticOutput};
+use crate::hir::{init_logger, replace_handler};
+use crate::lint_config::{install_config, LintConfig, LintConfigGuard};
+use crate::queries::{override_queries, register_tools, QueryOverrides, QueryOverridesGuard};
+use crate::Error;
+
+pub(crate) struct Lints {
     callback: Arc<Box<dyn Fn(&mut LintStore) + Send + Sync + 'static>>,
     /// If one of these files is modified, the linter needs to be re-run.
     tracked_files: Arc<Vec<String>>,
+    /// Replaces the input parsed from the arguments (it's `None` when using `with_lints`).
+    input: Option<Input>,
+    path_options: PathOptions,
+    file_loader: Option<Box<dyn FileLoader + Send + Sync>>,
+    query_overrides: QueryOverrides,
+    diagnostic_output: DiagnosticOutput,
+    errors: DiagnosticCollector,
+    lint_config: Option<Arc<LintConfig>>,
+}
+
+impl Lints {
+    pub(crate) fn new<F: Fn(&mut LintStore) + Send + Sync + 'static>(
+        callback: F,
+        config: &AnalysisConfig,
+        errors: DiagnosticCollector,
+    ) -> Self {
+        Self {
+            callback: Arc::new(Box::new(callback)),
+            tracked_files: Arc::new(config.tracked_files.clone()),
+            input: config.input(),
+            path_options: config.path_options().clone(),
+            file_loader: config.file_loader(),
+            // The lints require the bodies to be type checked.
+            query_overrides: config.query_overrides(false),
+            diagnostic_output: config.diagnostic_output.clone(),
+            errors,
+            lint_config: config.lint_config.clone().map(Arc::new),
+        }
+    }
 }
 
 impl Callbacks for Lints {
     fn config(&mut self, config: &mut Config) {
+        if let Some(input) = self.input.take() {
+            config.input = input;
+        }
+        self.path_options.apply(&mut config.opts);
+        if let Some(file_loader) = self.file_loader.take() {
+            config.file_loader = Some(file_loader);
+        }
+        if let Some(lint_config) = &self.lint_config {
+            // The levels of the command line are applied after the ones of the configuration.
+            let levels = lint_config
+                .lint_levels()
+                .map(|(name, level)| (name.to_owned(), level));
+            config.opts.lint_opts.splice(0..0, levels);
+        }
         // Should always be `None` but just in case...
         let previous = config.register_lints.take();
 
+        let query_overrides = std::mem::take(&mut self.query_overrides);
+        // Always needed: the tools of the lints are registered by overriding a query.
+        config.override_queries = Some(override_queries);
         let tracked_files = Arc::clone(&self.tracked_files);
+        let replace_handler = replace_handler(
+            &config.opts,
+            self.diagnostic_output.clone(),
+            self.errors.clone(),
+        );
         config.parse_sess_created = Some(Box::new(move |parse_sess| {
+            replace_handler(parse_sess);
+            query_overrides.install();
             // In here, we insert the files that, if modified, will tell cargo that the command
             // needs to be re-run.
             if tracked_files.is_empty() {
@@ -33,11 +95,33 @@ impl Callbacks for Lints {
             }
         }));
         let callback = Arc::clone(&self.callback);
+        let diagnostic_output = self.diagnostic_output.clone();
+        let lint_config = self.lint_config.clone();
+        // The compiler drops `register_lints` in its thread at the end of the compilation, so the
+        // guards reset the overrides installed above and the lint configuration installed below.
+        let query_overrides_guard = QueryOverridesGuard;
+        let lint_config_guard = LintConfigGuard;
         config.register_lints = Some(Box::new(move |sess, lint_store| {
+            let _ = (&query_overrides_guard, &lint_config_guard);
+            if let Some(lint_config) = &lint_config {
+                install_config(Arc::clone(lint_config));
+            }
             if let Some(previous) = &previous {
                 (previous)(sess, lint_store);
             }
             (*callback)(lint_store);
+            // The tools of the lints and of the groups need to be registered, otherwise rustc
+            // rejects them in the lint attributes and on the command line.
+            let lint_names = lint_store.get_lints().iter().map(|lint| lint.name);
+            let group_names = lint_store.get_lint_groups().map(|(name, _, _)| name);
+            register_tools(
+                lint_names
+                    .chain(group_names)
+                    .filter_map(|name| name.split_once("::").map(|(tool, _)| tool)),
+            );
+            if let DiagnosticOutput::Sarif(sarif) = &diagnostic_output {
+                sarif.register_rules(lint_store, sess.edition());
+            }
         }));
     }
 }
@@ -46,6 +130,8 @@ impl Callbacks for Lints {
 ///
 /// * `args` is what is provided to the compiler.
 /// * `tracked_files` is the files which will trigger a re-compilation if they are modified.
+/// * `diagnostic_output` allows you to choose if the diagnostics should be printed on stderr or
+///   captured (take a look at [`DiagnosticOutput`] for more information).
 /// * `callback` is called when everything is setup. This is where you will register your lints
 ///   before they are run by rustc directly. It provides a mutable reference to the [`LintStore`]
 ///   type.
@@ -57,23 +143,48 @@ impl Callbacks for Lints {
 /// will simply fail to compile and the `callback` won't be called. A good example of the list
 /// of the expected arguments can be seen when you run `cargo build -v`.
 ///
+/// If you don't want to write the rustc arguments yourself, take a look at [`with_lints_config`].
+///
 /// [`LintStore`]: https://doc.rust-lang.org/nightly/nightly-rustc/rustc_lint/struct.LintStore.html
 pub fn with_lints<F: Fn(&mut LintStore) + Send + Sync + 'static>(
     args: &[String],
     tracked_files: Vec<String>,
+    diagnostic_output: DiagnosticOutput,
     callback: F,
-) -> Result<(), ErrorGuaranteed> {
-    let handler = EarlyErrorHandler::new(ErrorOutputType::default());
-    rustc_driver::init_rustc_env_logger(&handler);
-    rustc_driver::catch_fatal_errors(move || {
-        rustc_driver::RunCompiler::new(
-            args,
-            &mut Lints {
-                callback: Arc::new(Box::new(callback)),
-                tracked_files: Arc::new(tracked_files),
-            },
-        )
-        .run()
-        .map(|_| ())
-    })?
+) -> Result<(), Error> {
+    // The first argument is the binary name.
+    let mut config = AnalysisConfig::from_args(args.get(1..).unwrap_or_default())
+        .diagnostic_output(diagnostic_output);
+    config.tracked_files = tracked_files;
+    with_lints_config(&config, callback)
+}
+
+/// Same as [`with_lints`], but the compiler is configured with an [`AnalysisConfig`] instead of
+/// command line arguments.
+pub fn with_lints_config<F: Fn(&mut LintStore) + Send + Sync + 'static>(
+    config: &AnalysisConfig,
+    callback: F,
+) -> Result<(), Error> {
+    let errors = DiagnosticCollector::new();
+    let mut lints = Lints::new(callback, config, errors.clone());
+    let mut args = vec!["rustc".to_owned()];
+    args.extend(config.to_args());
+
+    run_compiler(&args, &mut lints, &errors)
+}
+
+/// Runs rustc with the given `callbacks` and converts its failures into [`Error`].
+pub(crate) fn run_compiler<E>(
+    args: &[String],
+    callbacks: &mut (dyn Callbacks + Send),
+    errors: &DiagnosticCollector,
+) -> Result<(), Error<E>> {
+    catch_unwind(AssertUnwindSafe(|| {
+        let handler = EarlyErrorHandler::new(ErrorOutputType::default());
+        init_logger(&handler);
+        rustc_driver::catch_fatal_errors(|| rustc_driver::RunCompiler::new(args, callbacks).run())
+            .and_then(|result| result)
+            .map_err(|_| Error::Compilation(errors.take()))
+    }))
+    .unwrap_or_else(|payload| Err(Error::Panic(payload)))
 }
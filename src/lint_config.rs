@@ -0,0 +1,718 @@
+use rustc_lint::{Lint, LintContext};
+use rustc_lint_defs::Level;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::sync::Arc;
+
+/// Level of a lint set in a [`LintConfig`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    ForceWarn,
+    Deny,
+    Forbid,
+}
+
+impl LintLevel {
+    fn from_str(level: &str) -> Option<Self> {
+        match level {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "force-warn" => Some(Self::ForceWarn),
+            "deny" => Some(Self::Deny),
+            "forbid" => Some(Self::Forbid),
+            _ => None,
+        }
+    }
+
+    fn to_level(self) -> Level {
+        match self {
+            Self::Allow => Level::Allow,
+            Self::Warn => Level::Warn,
+            Self::ForceWarn => Level::ForceWarn(None),
+            Self::Deny => Level::Deny,
+            Self::Forbid => Level::Forbid,
+        }
+    }
+}
+
+/// Value of a lint option in a [`LintConfig`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum LintOption {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<LintOption>),
+}
+
+/// Conversion of a [`LintOption`] into a Rust type, used by [`LintConfig::option`].
+pub trait FromLintOption: Sized {
+    /// Returns `None` if `option` doesn't have the expected type (or doesn't fit into it).
+    fn from_lint_option(option: &LintOption) -> Option<Self>;
+}
+
+impl FromLintOption for String {
+    fn from_lint_option(option: &LintOption) -> Option<Self> {
+        match option {
+            LintOption::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromLintOption for bool {
+    fn from_lint_option(option: &LintOption) -> Option<Self> {
+        match option {
+            LintOption::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl FromLintOption for f64 {
+    fn from_lint_option(option: &LintOption) -> Option<Self> {
+        match option {
+            LintOption::Float(f) => Some(*f),
+            LintOption::Integer(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! integer_option {
+    ($($ty:ty),*) => {
+        $(
+            impl FromLintOption for $ty {
+                fn from_lint_option(option: &LintOption) -> Option<Self> {
+                    match option {
+                        LintOption::Integer(i) => (*i).try_into().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_option!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+
+impl<T: FromLintOption> FromLintOption for Vec<T> {
+    fn from_lint_option(option: &LintOption) -> Option<Self> {
+        match option {
+            LintOption::Array(values) => values.iter().map(T::from_lint_option).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// Error returned when a [`LintConfig`] file cannot be loaded.
+#[derive(Debug)]
+pub enum LintConfigError {
+    /// The file cannot be read.
+    Io(PathBuf, io::Error),
+    /// The file isn't valid. `line` and `column` are 1-based, and `column` is in characters.
+    Parse {
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for LintConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, error) => write!(f, "cannot read `{}`: {}", path.display(), error),
+            Self::Parse {
+                path: Some(path),
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            Self::Parse {
+                path: None,
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct LintSettings {
+    level: Option<LintLevel>,
+    options: BTreeMap<String, LintOption>,
+}
+
+/// Levels and options of the lints, usually loaded from a configuration file of your tool (like
+/// `clippy.toml`). Each lint has its own table, with its level and its options:
+///
+/// ```toml
+/// ["mytool::too_many_generics"]
+/// level = "deny"
+/// max = 2
+/// ignored-names = ["Foo", "Bar"]
+/// ```
+///
+/// Only a subset of TOML is supported: tables whose name is a lint name (in lowercase, with the
+/// tool name), and strings, integers, floats, booleans and arrays as values. The levels are
+/// `"allow"`, `"warn"`, `"force-warn"`, `"deny"` and `"forbid"`.
+///
+/// Use [`AnalysisConfig::lint_config`](crate::AnalysisConfig::lint_config) to run the lints with
+/// it. The levels are applied before the ones of the command line (so `-A` flags still win), and
+/// the options are available from the lint passes with [`LintConfigExt::lint_config`].
+#[derive(Clone, Debug, Default)]
+pub struct LintConfig {
+    path: Option<PathBuf>,
+    lints: BTreeMap<String, LintSettings>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the configuration from the file at `path`.
+    pub fn from_file<P: Into<PathBuf>>(path: P) -> Result<Self, LintConfigError> {
+        let path = path.into();
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) => return Err(LintConfigError::Io(path, error)),
+        };
+        match Self::parse(&source) {
+            Ok(config) => Ok(Self {
+                path: Some(path),
+                ..config
+            }),
+            Err(LintConfigError::Parse {
+                line,
+                column,
+                message,
+                ..
+            }) => Err(LintConfigError::Parse {
+                path: Some(path),
+                line,
+                column,
+                message,
+            }),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Parses the content of a configuration file.
+    pub fn parse(source: &str) -> Result<Self, LintConfigError> {
+        ConfigParser::new(source)
+            .parse()
+            .map_err(|((line, column), message)| LintConfigError::Parse {
+                path: None,
+                line,
+                column,
+                message,
+            })
+    }
+
+    /// The file this configuration comes from, if it has been loaded with
+    /// [`LintConfig::from_file`].
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the level set for the lint named `lint_name` (in lowercase, like
+    /// `"mytool::too_many_generics"`).
+    pub fn level(&self, lint_name: &str) -> Option<LintLevel> {
+        self.lints.get(lint_name).and_then(|lint| lint.level)
+    }
+
+    /// Returns the option `key` of `lint`, or `None` if it isn't set or doesn't have the type `T`.
+    ///
+    /// ```ignore
+    /// let max = cx.lint_config().option::<usize>(TOO_MANY_GENERICS, "max").unwrap_or(3);
+    /// ```
+    pub fn option<T: FromLintOption>(&self, lint: &Lint, key: &str) -> Option<T> {
+        self.option_value(&lint.name_lower(), key)
+            .and_then(T::from_lint_option)
+    }
+
+    /// Returns the option `key` of the lint named `lint_name`, whatever its type.
+    pub fn option_value(&self, lint_name: &str, key: &str) -> Option<&LintOption> {
+        self.lints.get(lint_name)?.options.get(key)
+    }
+
+    pub(crate) fn lint_levels(&self) -> impl Iterator<Item = (&str, Level)> {
+        self.lints
+            .iter()
+            .filter_map(|(name, lint)| Some((name.as_str(), lint.level?.to_level())))
+    }
+}
+
+thread_local! {
+    static RUNNING_CONFIG: RefCell<Option<Arc<LintConfig>>> = RefCell::new(None);
+}
+
+/// Sets the configuration of the compilation running on the current thread. It needs to be
+/// called in the compiler thread, so from the `register_lints` callback.
+pub(crate) fn install_config(config: Arc<LintConfig>) {
+    RUNNING_CONFIG.with(|running| *running.borrow_mut() = Some(config));
+}
+
+/// Resets the configuration of the current thread when dropped, so the one of a compilation
+/// doesn't leak into the next one running on the same thread. It needs to be dropped in the
+/// compiler thread once the compilation is over.
+pub(crate) struct LintConfigGuard;
+
+impl Drop for LintConfigGuard {
+    fn drop(&mut self) {
+        // The thread local may already be destroyed if the guard is dropped with the thread.
+        let _ = RUNNING_CONFIG.try_with(|running| running.take());
+    }
+}
+
+/// Gives access to the [`LintConfig`] of the current run from the lint passes (`EarlyContext` and
+/// `LateContext`).
+pub trait LintConfigExt {
+    /// Returns the [`LintConfig`] provided with
+    /// [`AnalysisConfig::lint_config`](crate::AnalysisConfig::lint_config), or an empty one.
+    fn lint_config(&self) -> Arc<LintConfig>;
+}
+
+impl<T: LintContext> LintConfigExt for T {
+    fn lint_config(&self) -> Arc<LintConfig> {
+        // The lint passes run in the compiler thread, where the configuration is installed.
+        RUNNING_CONFIG
+            .with(|running| running.borrow().clone())
+            .unwrap_or_default()
+    }
+}
+
+/// 1-based line and column (in characters).
+type Position = (usize, usize);
+type ParseResult<T> = Result<T, (Position, String)>;
+
+/// Parser of the TOML subset supported by [`LintConfig`].
+struct ConfigParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> ConfigParser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Position of the next character.
+    fn position(&self) -> Position {
+        (self.line, self.column)
+    }
+
+    /// Returns an error at the position of the next character.
+    fn error<T, S: Into<String>>(&self, message: S) -> ParseResult<T> {
+        Self::error_at(self.position(), message)
+    }
+
+    fn error_at<T, S: Into<String>>(position: Position, message: S) -> ParseResult<T> {
+        Err((position, message.into()))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        match c {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            }
+            Some(_) => self.column += 1,
+            None => {}
+        }
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips the spaces and the comment until the end of the line. If `newlines` is `true`, the
+    /// following lines are skipped too if they're empty.
+    fn skip_whitespace(&mut self, newlines: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {}
+                '\n' if newlines => {}
+                '#' => {
+                    while self.chars.peek().map_or(false, |&c| c != '\n') {
+                        self.next();
+                    }
+                    continue;
+                }
+                _ => return,
+            }
+            self.next();
+        }
+    }
+
+    fn expect_end_of_line(&mut self) -> ParseResult<()> {
+        self.skip_whitespace(false);
+        match self.chars.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some(&c) => self.error(format!("expected a new line, found `{}`", c)),
+        }
+    }
+
+    fn parse(mut self) -> ParseResult<LintConfig> {
+        let mut config = LintConfig::default();
+        let mut current = None;
+        loop {
+            self.skip_whitespace(true);
+            match self.chars.peek() {
+                None => return Ok(config),
+                Some('[') => {
+                    self.next();
+                    self.skip_whitespace(false);
+                    let position = self.position();
+                    let name = self.parse_key()?;
+                    self.skip_whitespace(false);
+                    if !self.eat(']') {
+                        return self.error("expected `]`");
+                    }
+                    self.expect_end_of_line()?;
+                    if config.lints.contains_key(&name) {
+                        return Self::error_at(position, format!("duplicated lint `{}`", name));
+                    }
+                    config.lints.insert(name.clone(), LintSettings::default());
+                    current = Some(name);
+                }
+                Some(_) => {
+                    let key_position = self.position();
+                    let key = self.parse_key()?;
+                    self.skip_whitespace(false);
+                    if !self.eat('=') {
+                        return self.error("expected `=`");
+                    }
+                    self.skip_whitespace(false);
+                    let value_position = self.position();
+                    let value = self.parse_value()?;
+                    self.expect_end_of_line()?;
+
+                    let Some(lint) = current.as_ref().and_then(|name| config.lints.get_mut(name))
+                    else {
+                        return Self::error_at(
+                            key_position,
+                            format!(
+                                "`{}` must be in the table of a lint (like \
+                                 `[\"mytool::lint_name\"]`)",
+                                key
+                            ),
+                        );
+                    };
+                    if key == "level" {
+                        let level = match &value {
+                            LintOption::String(level) => LintLevel::from_str(level),
+                            _ => None,
+                        };
+                        match level {
+                            Some(level) if lint.level.is_none() => lint.level = Some(level),
+                            Some(_) => {
+                                return Self::error_at(key_position, "duplicated key `level`")
+                            }
+                            None => {
+                                return Self::error_at(
+                                    value_position,
+                                    "expected `\"allow\"`, `\"warn\"`, `\"force-warn\"`, \
+                                     `\"deny\"` or `\"forbid\"`",
+                                )
+                            }
+                        }
+                    } else if lint.options.insert(key.clone(), value).is_some() {
+                        return Self::error_at(key_position, format!("duplicated key `{}`", key));
+                    }
+                }
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> ParseResult<String> {
+        match self.chars.peek() {
+            Some('"') | Some('\'') => self.parse_string(),
+            _ => {
+                let mut key = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !c.is_ascii_alphanumeric() && c != '_' && c != '-' {
+                        break;
+                    }
+                    key.push(c);
+                    self.next();
+                }
+                if key.is_empty() {
+                    return self.error("expected a key");
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> ParseResult<LintOption> {
+        match self.chars.peek() {
+            Some('"') | Some('\'') => self.parse_string().map(LintOption::String),
+            Some('[') => {
+                self.next();
+                let mut values = Vec::new();
+                loop {
+                    self.skip_whitespace(true);
+                    if self.eat(']') {
+                        return Ok(LintOption::Array(values));
+                    }
+                    values.push(self.parse_value()?);
+                    self.skip_whitespace(true);
+                    if !self.eat(',') {
+                        self.skip_whitespace(true);
+                        if self.eat(']') {
+                            return Ok(LintOption::Array(values));
+                        }
+                        return self.error("expected `,` or `]`");
+                    }
+                }
+            }
+            _ => {
+                let position = self.position();
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !c.is_ascii_alphanumeric() && !matches!(c, '_' | '-' | '+' | '.') {
+                        break;
+                    }
+                    word.push(c);
+                    self.next();
+                }
+                match word.as_str() {
+                    "" => self.error("expected a value"),
+                    "true" => Ok(LintOption::Boolean(true)),
+                    "false" => Ok(LintOption::Boolean(false)),
+                    _ => {
+                        let number = word.replace('_', "");
+                        if let Ok(i) = number.parse() {
+                            Ok(LintOption::Integer(i))
+                        } else if let Ok(f) = number.parse() {
+                            Ok(LintOption::Float(f))
+                        } else {
+                            Self::error_at(position, format!("invalid value `{}`", word))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parses a basic (`"..."`) or a literal (`'...'`) string, on a single line.
+    fn parse_string(&mut self) -> ParseResult<String> {
+        let position = self.position();
+        let quote = self.next().unwrap();
+        let mut s = String::new();
+        loop {
+            let escape_position = self.position();
+            match self.next() {
+                None | Some('\n') => return Self::error_at(position, "unterminated string"),
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') if quote == '"' => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some(c @ ('u' | 'U')) => {
+                            let len = if c == 'u' { 4 } else { 8 };
+                            let hex = (0..len).filter_map(|_| self.next()).collect::<String>();
+                            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                                Some(c) => c,
+                                None => {
+                                    return Self::error_at(
+                                        escape_position,
+                                        format!("invalid escape `\\{c}{hex}`"),
+                                    )
+                                }
+                            }
+                        }
+                        _ => return Self::error_at(escape_position, "invalid escape"),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the line, the column and the message of the error of `source`.
+    fn error(source: &str) -> (usize, usize, String) {
+        match LintConfig::parse(source) {
+            Err(LintConfigError::Parse {
+                path: None,
+                line,
+                column,
+                message,
+            }) => (line, column, message),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let config = LintConfig::parse(
+            "# comment\n\
+             [\"mytool::a\"] # comment\n\
+             level = 'deny'\n\
+             max = 1_000\n\
+             ratio = -0.5\n\
+             enabled = true\n\
+             names = [\n  \"a\\\"b\\u00e9\",\n  'c\\d', # comment\n]\n\
+             \n\
+             [mytool-b]\n\
+             empty = []\n",
+        )
+        .unwrap();
+        assert_eq!(config.level("mytool::a"), Some(LintLevel::Deny));
+        assert_eq!(config.level("mytool-b"), None);
+        assert_eq!(config.level("mytool::c"), None);
+        assert_eq!(
+            config.option_value("mytool::a", "max"),
+            Some(&LintOption::Integer(1000)),
+        );
+        assert_eq!(
+            config.option_value("mytool::a", "ratio"),
+            Some(&LintOption::Float(-0.5)),
+        );
+        assert_eq!(
+            config.option_value("mytool::a", "enabled"),
+            Some(&LintOption::Boolean(true)),
+        );
+        assert_eq!(
+            config.option_value("mytool::a", "names"),
+            Some(&LintOption::Array(vec![
+                LintOption::String("a\"bé".to_owned()),
+                LintOption::String("c\\d".to_owned()),
+            ])),
+        );
+        assert_eq!(
+            config.option_value("mytool-b", "empty"),
+            Some(&LintOption::Array(Vec::new())),
+        );
+        assert_eq!(
+            config.lint_levels().collect::<Vec<_>>(),
+            [("mytool::a", Level::Deny)],
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            error("[a\nlevel = 'deny'"),
+            (1, 3, "expected `]`".to_owned())
+        );
+        assert_eq!(
+            error("[a] b"),
+            (1, 5, "expected a new line, found `b`".to_owned())
+        );
+        assert_eq!(error("[a]\nb 1"), (2, 3, "expected `=`".to_owned()));
+        assert_eq!(error("[a]\nb ="), (2, 4, "expected a value".to_owned()));
+        assert_eq!(
+            error("[a]\nb = 1x"),
+            (2, 5, "invalid value `1x`".to_owned())
+        );
+        assert_eq!(
+            error("[a]\nb = [1 2]"),
+            (2, 8, "expected `,` or `]`".to_owned())
+        );
+        assert_eq!(
+            error("[a]\nb = \"c\nd\""),
+            (2, 5, "unterminated string".to_owned())
+        );
+        assert_eq!(
+            error("[a]\nb = \"c\\q\""),
+            (2, 7, "invalid escape".to_owned())
+        );
+        assert_eq!(
+            error("[a]\nb = \"é\\u00zz\""),
+            (2, 7, "invalid escape `\\u00zz`".to_owned()),
+        );
+        assert_eq!(
+            error("b = 1"),
+            (
+                1,
+                1,
+                "`b` must be in the table of a lint (like `[\"mytool::lint_name\"]`)".to_owned(),
+            ),
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(
+            error("[a]\n[ \"a\" ]"),
+            (2, 3, "duplicated lint `a`".to_owned()),
+        );
+        assert_eq!(
+            error("[a]\nb = 1\n  b = 2"),
+            (3, 3, "duplicated key `b`".to_owned()),
+        );
+        assert_eq!(
+            error("[a]\nlevel = 'warn'\nlevel = 'deny'"),
+            (3, 1, "duplicated key `level`".to_owned()),
+        );
+    }
+
+    #[test]
+    fn levels() {
+        let config = LintConfig::parse(
+            "[a]\nlevel = 'allow'\n[b]\nlevel = 'force-warn'\n[c]\nlevel = 'forbid'\n",
+        )
+        .unwrap();
+        assert_eq!(config.level("a"), Some(LintLevel::Allow));
+        assert_eq!(config.level("b"), Some(LintLevel::ForceWarn));
+        assert_eq!(config.level("c"), Some(LintLevel::Forbid));
+
+        let expected = "expected `\"allow\"`, `\"warn\"`, `\"force-warn\"`, `\"deny\"` or \
+                        `\"forbid\"`"
+            .to_owned();
+        assert_eq!(error("[a]\nlevel = 'error'"), (2, 9, expected.clone()));
+        assert_eq!(error("[a]\nlevel = 1"), (2, 9, expected));
+    }
+
+    #[test]
+    fn display() {
+        let error = LintConfig::parse("[a]\nlevel = 'error'").unwrap_err();
+        assert!(error.to_string().starts_with("line 2, column 9: expected"));
+        let error = LintConfigError::Parse {
+            path: Some(PathBuf::from("lints.toml")),
+            line: 2,
+            column: 9,
+            message: "invalid".to_owned(),
+        };
+        assert_eq!(error.to_string(), "lints.toml:2:9: invalid");
+    }
+}
@@ -0,0 +1,297 @@
+use rustc_ast::ast::{FloatTy, IntTy, LitFloatType, LitIntType, UintTy};
+use rustc_lexer::unescape::{self, CStrUnit, EscapeError, Mode};
+use rustc_lexer::{Base, LiteralKind, TokenKind};
+
+use std::ffi::CString;
+use std::ops::Range;
+
+use crate::tokens::{Token, TokenError};
+
+/// Value of a literal token, returned by [`unescape_literal`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Char(char),
+    Byte(u8),
+    /// A string literal, raw or not.
+    Str(String),
+    /// A byte string literal, raw or not.
+    ByteStr(Vec<u8>),
+    /// A C string literal, raw or not.
+    CStr(CString),
+    Int(u128, LitIntType),
+    /// Float literals with the `f32` suffix are parsed as `f32` and then converted.
+    Float(f64, LitFloatType),
+}
+
+/// Error found in a literal token by [`unescape_literal`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct LiteralError {
+    pub kind: LiteralErrorKind,
+    /// Byte range of the error in the source code (the escape sequence, the suffix, etc).
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LiteralErrorKind {
+    /// The lexer already found an error in the token (like an unterminated literal).
+    Token(TokenError),
+    /// An invalid escape sequence or character.
+    Escape(EscapeError),
+    /// A suffix on a literal which isn't a number (like `"a"foo`).
+    InvalidSuffix,
+    /// An integer suffix which isn't an integer type (like `1u7`).
+    InvalidIntSuffix,
+    /// A float suffix which isn't a float type (like `1.0f16`).
+    InvalidFloatSuffix,
+    /// A float which isn't written in base 10, the base is given.
+    NonDecimalFloat(u32),
+    /// A digit which is too large for the base of the integer (like the `2` in `0b102`).
+    InvalidDigit(u32),
+    /// An integer which doesn't fit in a `u128`.
+    IntTooLarge,
+    /// A nul character in a C string literal.
+    NulInCStr,
+}
+
+/// Returns the value of a literal token returned by [`tokens`](fn@crate::tokens), with the escape
+/// sequences decoded the same way as rustc does (using `rustc_lexer::unescape`). Returns `None` if
+/// `token` isn't a literal.
+///
+/// Like rustc, all the errors of the literal are returned (and not only the first one). Note that
+/// `true` and `false` are identifiers for the lexer, so they aren't handled here.
+pub fn unescape_literal(token: &Token<'_>) -> Option<Result<Literal, Vec<LiteralError>>> {
+    let TokenKind::Literal { kind, suffix_start } = token.kind else {
+        return None;
+    };
+    if let Some(error) = token.error {
+        return Some(Err(vec![LiteralError {
+            kind: LiteralErrorKind::Token(error),
+            range: token.range.clone(),
+        }]));
+    }
+
+    let suffix_start = suffix_start as usize;
+    let literal = LiteralParser {
+        text: token.text,
+        offset: token.range.start,
+        suffix_start,
+        errors: Vec::new(),
+    };
+    let suffix = &token.text[suffix_start..];
+    let result = match kind {
+        LiteralKind::Int { base, .. } => literal.int(base, suffix),
+        LiteralKind::Float { base, .. } => literal.float(base, suffix),
+        _ if !suffix.is_empty() => Err(vec![LiteralError {
+            kind: LiteralErrorKind::InvalidSuffix,
+            range: literal.range(suffix_start..token.text.len()),
+        }]),
+        LiteralKind::Char { .. } => literal.char(Mode::Char, 1),
+        LiteralKind::Byte { .. } => literal.char(Mode::Byte, 2),
+        LiteralKind::Str { .. } => literal.str(Mode::Str, 1),
+        LiteralKind::ByteStr { .. } => literal.byte_str(Mode::ByteStr, 2),
+        LiteralKind::CStr { .. } => literal.c_str(Mode::CStr, 2),
+        // The lexer reports the raw strings without `n_hashes`.
+        LiteralKind::RawStr { n_hashes } => literal.str(Mode::RawStr, raw_prefix_len(1, n_hashes)),
+        LiteralKind::RawByteStr { n_hashes } => {
+            literal.byte_str(Mode::RawByteStr, raw_prefix_len(2, n_hashes))
+        }
+        LiteralKind::RawCStr { n_hashes } => {
+            literal.c_str(Mode::RawCStr, raw_prefix_len(2, n_hashes))
+        }
+    };
+    Some(result)
+}
+
+/// Length of `r#"` (or `br#"`, `cr#"`) where `prefix_len` is the length of the part before the
+/// `#`s.
+fn raw_prefix_len(prefix_len: usize, n_hashes: Option<u8>) -> usize {
+    prefix_len + n_hashes.unwrap_or(0) as usize + 1
+}
+
+struct LiteralParser<'a> {
+    /// Text of the token, including the suffix.
+    text: &'a str,
+    /// Offset of the token in the source code.
+    offset: usize,
+    suffix_start: usize,
+    errors: Vec<LiteralError>,
+}
+
+impl<'a> LiteralParser<'a> {
+    /// Converts a range in the token into a range in the source code.
+    fn range(&self, range: Range<usize>) -> Range<usize> {
+        self.offset + range.start..self.offset + range.end
+    }
+
+    fn error(&mut self, kind: LiteralErrorKind, range: Range<usize>) {
+        self.errors.push(LiteralError {
+            kind,
+            range: self.range(range),
+        });
+    }
+
+    /// Returns the content of a quoted literal and its offset in the token. `prefix_len` is the
+    /// length of the part before the content (`"`, `b"`, `r#"`, etc).
+    fn content(&self, prefix_len: usize) -> (&'a str, usize) {
+        // The closing part is the prefix without its letters (`"#` for `r#"`).
+        let closing_len = self.text[..prefix_len]
+            .trim_start_matches(['b', 'c', 'r'])
+            .len();
+        (
+            &self.text[prefix_len..self.suffix_start - closing_len],
+            prefix_len,
+        )
+    }
+
+    fn finish<T>(self, value: T) -> Result<T, Vec<LiteralError>> {
+        if self.errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    /// Calls `callback` with the characters of the content, and stores the fatal errors.
+    fn unescape(&mut self, mode: Mode, prefix_len: usize, mut callback: impl FnMut(char)) {
+        let (content, content_start) = self.content(prefix_len);
+        unescape::unescape_literal(content, mode, &mut |range, result| match result {
+            Ok(c) => callback(c),
+            // The other ones are warnings.
+            Err(error) if error.is_fatal() => self.error(
+                LiteralErrorKind::Escape(error),
+                content_start + range.start..content_start + range.end,
+            ),
+            Err(_) => {}
+        });
+    }
+
+    fn char(mut self, mode: Mode, prefix_len: usize) -> Result<Literal, Vec<LiteralError>> {
+        let mut value = None;
+        self.unescape(mode, prefix_len, |c| value = Some(c));
+        let value = value.unwrap_or_default();
+        self.finish(if mode == Mode::Byte {
+            Literal::Byte(unescape::byte_from_char(value))
+        } else {
+            Literal::Char(value)
+        })
+    }
+
+    fn str(mut self, mode: Mode, prefix_len: usize) -> Result<Literal, Vec<LiteralError>> {
+        let mut value = String::new();
+        self.unescape(mode, prefix_len, |c| value.push(c));
+        self.finish(Literal::Str(value))
+    }
+
+    fn byte_str(mut self, mode: Mode, prefix_len: usize) -> Result<Literal, Vec<LiteralError>> {
+        let mut value = Vec::new();
+        self.unescape(mode, prefix_len, |c| {
+            value.push(unescape::byte_from_char(c))
+        });
+        self.finish(Literal::ByteStr(value))
+    }
+
+    fn c_str(mut self, mode: Mode, prefix_len: usize) -> Result<Literal, Vec<LiteralError>> {
+        let (content, content_start) = self.content(prefix_len);
+        let mut value = Vec::new();
+        unescape::unescape_c_string(content, mode, &mut |range, result| {
+            let range = content_start + range.start..content_start + range.end;
+            match result {
+                Ok(CStrUnit::Byte(0) | CStrUnit::Char('\0')) => {
+                    self.error(LiteralErrorKind::NulInCStr, range)
+                }
+                Ok(CStrUnit::Byte(b)) => value.push(b),
+                Ok(CStrUnit::Char(c)) => {
+                    value.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+                }
+                Err(error) if error.is_fatal() => {
+                    self.error(LiteralErrorKind::Escape(error), range)
+                }
+                Err(_) => {}
+            }
+        });
+        // The nul characters have been reported.
+        let value = CString::new(value).unwrap_or_default();
+        self.finish(Literal::CStr(value))
+    }
+
+    fn int(mut self, base: Base, suffix: &str) -> Result<Literal, Vec<LiteralError>> {
+        let ty = match suffix {
+            "" => LitIntType::Unsuffixed,
+            "isize" => LitIntType::Signed(IntTy::Isize),
+            "i8" => LitIntType::Signed(IntTy::I8),
+            "i16" => LitIntType::Signed(IntTy::I16),
+            "i32" => LitIntType::Signed(IntTy::I32),
+            "i64" => LitIntType::Signed(IntTy::I64),
+            "i128" => LitIntType::Signed(IntTy::I128),
+            "usize" => LitIntType::Unsigned(UintTy::Usize),
+            "u8" => LitIntType::Unsigned(UintTy::U8),
+            "u16" => LitIntType::Unsigned(UintTy::U16),
+            "u32" => LitIntType::Unsigned(UintTy::U32),
+            "u64" => LitIntType::Unsigned(UintTy::U64),
+            "u128" => LitIntType::Unsigned(UintTy::U128),
+            // Like rustc, `1f32` is a float and `1fxx` an invalid float.
+            _ if suffix.starts_with('f') => return self.float(base, suffix),
+            _ => {
+                self.error(
+                    LiteralErrorKind::InvalidIntSuffix,
+                    self.suffix_start..self.text.len(),
+                );
+                return self.finish(Literal::Int(0, LitIntType::Unsuffixed));
+            }
+        };
+
+        let base = base as u32;
+        let digits_start = if base == 10 { 0 } else { 2 };
+        // The lexer accepts all the decimal digits in binary and octal literals.
+        for (i, c) in self.text[digits_start..self.suffix_start].char_indices() {
+            if c.to_digit(10).is_some_and(|digit| digit >= base) {
+                let start = digits_start + i;
+                self.error(LiteralErrorKind::InvalidDigit(base), start..start + 1);
+            }
+        }
+        if !self.errors.is_empty() {
+            return self.finish(Literal::Int(0, ty));
+        }
+
+        let digits = self.text[digits_start..self.suffix_start].replace('_', "");
+        let value = match u128::from_str_radix(&digits, base) {
+            Ok(value) => value,
+            Err(_) => {
+                self.error(LiteralErrorKind::IntTooLarge, 0..self.suffix_start);
+                0
+            }
+        };
+        self.finish(Literal::Int(value, ty))
+    }
+
+    fn float(mut self, base: Base, suffix: &str) -> Result<Literal, Vec<LiteralError>> {
+        if base != Base::Decimal {
+            self.error(
+                LiteralErrorKind::NonDecimalFloat(base as u32),
+                0..self.suffix_start,
+            );
+            return self.finish(Literal::Float(0.0, LitFloatType::Unsuffixed));
+        }
+        let ty = match suffix {
+            "" => LitFloatType::Unsuffixed,
+            "f32" => LitFloatType::Suffixed(FloatTy::F32),
+            "f64" => LitFloatType::Suffixed(FloatTy::F64),
+            _ => {
+                self.error(
+                    LiteralErrorKind::InvalidFloatSuffix,
+                    self.suffix_start..self.text.len(),
+                );
+                return self.finish(Literal::Float(0.0, LitFloatType::Unsuffixed));
+            }
+        };
+
+        // The lexer only accepts valid floats, so they can always be parsed.
+        let digits = self.text[..self.suffix_start].replace('_', "");
+        let value = if ty == LitFloatType::Suffixed(FloatTy::F32) {
+            digits.parse::<f32>().map(f64::from)
+        } else {
+            digits.parse::<f64>()
+        };
+        self.finish(Literal::Float(value.unwrap_or_default(), ty))
+    }
+}
@@ -0,0 +1,38 @@
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::mir::pretty::write_mir_pretty;
+use rustc_middle::mir::Body;
+use rustc_middle::ty::{InstanceDef, TyCtxt};
+
+/// Returns all the local items having a MIR body (functions, closures, constants, etc) along with
+/// their MIR. Functions get their optimized MIR and constants the MIR used for const evaluation.
+///
+/// The MIR is only available with [`AnalysisLevel::Full`](crate::AnalysisLevel::Full), take a look
+/// at `examples/mir.rs` for an example. At this level, the MIR built before the MIR passes
+/// (`tcx.mir_built`) has been stolen already and cannot be read anymore (take a look at
+/// [`AnalysisLevel::Full`](crate::AnalysisLevel::Full) to get it).
+pub fn local_mir_bodies(tcx: TyCtxt<'_>) -> impl Iterator<Item = (LocalDefId, &Body<'_>)> {
+    tcx.mir_keys(()).iter().map(move |&def_id| {
+        (
+            def_id,
+            tcx.instance_mir(InstanceDef::Item(def_id.to_def_id())),
+        )
+    })
+}
+
+/// Returns the textual MIR of `def_id` (and of its promoted constants), as displayed by
+/// `rustc -Zunpretty=mir`.
+pub fn mir_to_string(tcx: TyCtxt<'_>, def_id: LocalDefId) -> String {
+    write_mir(tcx, Some(def_id))
+}
+
+/// Returns the textual MIR of the whole crate, as displayed by `rustc -Zunpretty=mir`.
+pub fn crate_mir_to_string(tcx: TyCtxt<'_>) -> String {
+    write_mir(tcx, None)
+}
+
+fn write_mir(tcx: TyCtxt<'_>, def_id: Option<LocalDefId>) -> String {
+    let mut output = Vec::new();
+    // Writing into a `Vec` cannot fail.
+    write_mir_pretty(tcx, def_id.map(LocalDefId::to_def_id), &mut output).unwrap();
+    String::from_utf8_lossy(&output).into_owned()
+}
@@ -0,0 +1,260 @@
+use rustc_ast::ast::{Attribute, Crate, Inline, Item, ItemKind, ModKind, NestedMetaItem};
+use rustc_ast::ptr::P;
+use rustc_ast::{token, CRATE_NODE_ID};
+use rustc_attr::cfg_matches;
+use rustc_errors::{error_code, DiagnosticBuilder, ErrorGuaranteed};
+use rustc_expand::module::{default_submod_path, DirOwnership, ModError, ModulePathSuccess};
+use rustc_parse::{new_parser_from_file, parse_cfg_attr};
+use rustc_session::parse::ParseSess;
+use rustc_span::symbol::{sym, Ident};
+use rustc_span::{Span, Symbol};
+
+use std::path::{Path, PathBuf};
+
+use crate::config::ParseConfig;
+use crate::recovery::{new_parser_recovering, parse_mod_recovering};
+
+/// Loads the out-of-line modules (`mod foo;`) of `krate` recursively, following the same rules
+/// as rustc (`rustc_expand::module`). `root_path` is the path of the crate root file.
+///
+/// Like rustc, the modules which cannot be found are reported and left unloaded. If a module
+/// cannot be parsed, its error is returned, unless `recover` is `true`: in this case the errors
+/// are emitted and the partial AST of the module is kept. The files ignored in `config` are always
+/// parsed this way.
+pub(crate) fn load_modules<'a>(
+    sess: &'a ParseSess,
+    config: &'a ParseConfig,
+    krate: &mut Crate,
+    root_path: &Path,
+    recover: bool,
+) -> Result<(), DiagnosticBuilder<'a, ErrorGuaranteed>> {
+    let dir_path = root_path.parent().unwrap_or(Path::new("")).to_owned();
+    let mut loader = ModuleLoader {
+        sess,
+        config,
+        file_path_stack: vec![root_path.to_owned()],
+        recover,
+    };
+    // The crate root is handled like a `mod.rs` file.
+    loader.load_items(
+        &mut krate.items,
+        &dir_path,
+        DirOwnership::Owned { relative: None },
+    )
+}
+
+struct ModuleLoader<'a> {
+    sess: &'a ParseSess,
+    config: &'a ParseConfig,
+    /// Files of the modules being loaded, to detect the circular modules.
+    file_path_stack: Vec<PathBuf>,
+    recover: bool,
+}
+
+impl<'a> ModuleLoader<'a> {
+    fn load_items(
+        &mut self,
+        items: &mut [P<Item>],
+        dir_path: &Path,
+        dir_ownership: DirOwnership,
+    ) -> Result<(), DiagnosticBuilder<'a, ErrorGuaranteed>> {
+        for item in items.iter_mut() {
+            let item = &mut **item;
+            let ItemKind::Mod(_, mod_kind) = &mut item.kind else {
+                continue;
+            };
+            if !self.is_cfg_enabled(&item.attrs) {
+                continue;
+            }
+            match mod_kind {
+                ModKind::Loaded(items, Inline::Yes, _) => {
+                    let (dir_path, dir_ownership) =
+                        self.inline_mod_dir_path(item.ident, &item.attrs, dir_path, dir_ownership);
+                    self.load_items(items, &dir_path, dir_ownership)?;
+                }
+                ModKind::Loaded(_, Inline::No, _) => {}
+                ModKind::Unloaded => {
+                    let Some(module) = self.mod_file_path(
+                        item.ident,
+                        item.span,
+                        &item.attrs,
+                        dir_path,
+                        dir_ownership,
+                    ) else {
+                        continue;
+                    };
+                    let file_path = module.file_path;
+                    if let Some(pos) = self.file_path_stack.iter().position(|p| *p == file_path) {
+                        let modules = self.file_path_stack[pos..]
+                            .iter()
+                            .chain(Some(&file_path))
+                            .map(|path| path.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(" -> ");
+                        self.sess
+                            .span_diagnostic
+                            .struct_span_err(item.span, format!("circular modules: {}", modules))
+                            .emit();
+                        continue;
+                    }
+
+                    let new_parser =
+                        || new_parser_from_file(self.sess, &file_path, Some(item.span));
+                    let recover = self.recover || self.config.is_ignored(&file_path);
+                    let (inner_attrs, mut items, spans) = if recover {
+                        let Some(mut parser) = new_parser_recovering(new_parser) else {
+                            continue;
+                        };
+                        parse_mod_recovering(&mut parser)
+                    } else {
+                        new_parser().parse_mod(&token::Eof)?
+                    };
+                    item.attrs.extend(inner_attrs);
+
+                    let dir_path = file_path.parent().unwrap_or(&file_path).to_owned();
+                    self.file_path_stack.push(file_path);
+                    let result = self.load_items(&mut items, &dir_path, module.dir_ownership);
+                    self.file_path_stack.pop();
+                    result?;
+                    *mod_kind = ModKind::Loaded(items, Inline::No, spans);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `false` if one of the `#[cfg]` attributes is disabled.
+    fn is_cfg_enabled(&self, attrs: &[Attribute]) -> bool {
+        attrs
+            .iter()
+            .filter(|attr| attr.has_name(sym::cfg))
+            .all(|attr| match attr.meta_item_list().as_deref() {
+                Some([NestedMetaItem::MetaItem(cfg)]) => {
+                    cfg_matches(cfg, self.sess, CRATE_NODE_ID, None)
+                }
+                // rustc will report it.
+                _ => true,
+            })
+    }
+
+    /// Returns the value of the first `#[path]` attribute, including the ones enabled by
+    /// `#[cfg_attr]`.
+    fn path_attr(&self, attrs: &[Attribute]) -> Option<Symbol> {
+        for attr in attrs {
+            if attr.has_name(sym::path) {
+                return attr.value_str();
+            }
+            if !attr.has_name(sym::cfg_attr) {
+                continue;
+            }
+            let Some((cfg, expanded_attrs)) = parse_cfg_attr(attr, self.sess) else {
+                continue;
+            };
+            if !cfg_matches(&cfg, self.sess, CRATE_NODE_ID, None) {
+                continue;
+            }
+            for (attr_item, span) in expanded_attrs {
+                if attr_item.path == sym::path {
+                    return attr_item.meta(span).and_then(|meta| meta.value_str());
+                }
+            }
+        }
+        None
+    }
+
+    /// Equivalent of `rustc_expand::module::mod_dir_path` for inline modules.
+    fn inline_mod_dir_path(
+        &self,
+        ident: Ident,
+        attrs: &[Attribute],
+        dir_path: &Path,
+        mut dir_ownership: DirOwnership,
+    ) -> (PathBuf, DirOwnership) {
+        if let Some(path) = self.path_attr(attrs) {
+            // For inline modules, the `#[path]` attribute is the directory path.
+            return (
+                dir_path.join(path.as_str()),
+                DirOwnership::Owned { relative: None },
+            );
+        }
+        let mut dir_path = dir_path.to_owned();
+        if let DirOwnership::Owned { relative } = &mut dir_ownership {
+            if let Some(ident) = relative.take() {
+                dir_path.push(ident.as_str());
+            }
+        }
+        dir_path.push(ident.as_str());
+        (dir_path, dir_ownership)
+    }
+
+    /// Equivalent of `rustc_expand::module::mod_file_path`. Reports the error and returns `None`
+    /// if the file of the module cannot be found.
+    fn mod_file_path(
+        &self,
+        ident: Ident,
+        span: Span,
+        attrs: &[Attribute],
+        dir_path: &Path,
+        dir_ownership: DirOwnership,
+    ) -> Option<ModulePathSuccess> {
+        let handler = &self.sess.span_diagnostic;
+        if let Some(path) = self.path_attr(attrs) {
+            let file_path = dir_path.join(path.as_str());
+            if !self.sess.source_map().file_exists(&file_path) {
+                handler
+                    .struct_span_err(
+                        span,
+                        format!("couldn't read {}: file not found", file_path.display()),
+                    )
+                    .emit();
+                return None;
+            }
+            // All `#[path]` files are treated as `mod.rs` files.
+            return Some(ModulePathSuccess {
+                file_path,
+                dir_ownership: DirOwnership::Owned { relative: None },
+            });
+        }
+
+        let relative = match dir_ownership {
+            DirOwnership::Owned { relative } => relative,
+            DirOwnership::UnownedViaBlock => None,
+        };
+        match default_submod_path(self.sess, ident, relative, dir_path) {
+            Ok(module) => Some(module),
+            Err(ModError::FileNotFound(name, default_path, secondary_path)) => {
+                handler
+                    .struct_span_err_with_code(
+                        span,
+                        format!("file not found for module `{}`", name),
+                        error_code!(E0583),
+                    )
+                    .help(format!(
+                        "to create the module `{}`, create file \"{}\" or \"{}\"",
+                        name,
+                        default_path.display(),
+                        secondary_path.display(),
+                    ))
+                    .emit();
+                None
+            }
+            Err(ModError::MultipleCandidates(name, default_path, secondary_path)) => {
+                handler
+                    .struct_span_err_with_code(
+                        span,
+                        format!(
+                            "file for module `{}` found at both \"{}\" and \"{}\"",
+                            name,
+                            default_path.display(),
+                            secondary_path.display(),
+                        ),
+                        error_code!(E0761),
+                    )
+                    .help("delete or rename one of them to remove the ambiguity")
+                    .emit();
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use rustc_ast::ast::{Crate, Expr, Item, Ty};
+use rustc_ast_pretty::pprust;
+use rustc_hir as hir;
+use rustc_hir::intravisit::Map;
+use rustc_middle::ty::TyCtxt;
+use rustc_session::parse::ParseSess;
+
+/// Returns the source code of `krate`, as printed by `rustc -Zunpretty=normal` but without the
+/// comments. It works on any AST, even one which has been modified or created from scratch.
+pub fn crate_to_string(krate: &Crate) -> String {
+    pprust::crate_to_string_for_macros(krate)
+}
+
+/// Same as [`crate_to_string`], but the comments of the crate root file are kept. `krate` must come
+/// from the parser which created `parse_sess` (like in [`with_ast_parser`](crate::with_ast_parser)
+/// callbacks).
+pub fn crate_to_string_with_comments(parse_sess: &ParseSess, krate: &Crate) -> String {
+    let source_map = parse_sess.source_map();
+    let root_file = source_map.lookup_source_file(krate.spans.inner_span.lo());
+    let source = root_file.src.as_deref().cloned().unwrap_or_default();
+    pprust::print_crate(
+        source_map,
+        krate,
+        root_file.name.clone(),
+        source,
+        &pprust::state::NoAnn,
+        false,
+        parse_sess.edition,
+        &parse_sess.attr_id_generator,
+    )
+}
+
+/// Returns the source code of an AST item.
+pub fn item_to_string(item: &Item) -> String {
+    pprust::item_to_string(item)
+}
+
+/// Returns the source code of an AST expression.
+pub fn expr_to_string(expr: &Expr) -> String {
+    pprust::expr_to_string(expr)
+}
+
+/// Returns the source code of an AST type.
+pub fn ty_to_string(ty: &Ty) -> String {
+    pprust::ty_to_string(ty)
+}
+
+/// Returns the HIR of the local crate as source code, as printed by `rustc -Zunpretty=hir`
+/// (including the comments of the crate root file).
+pub fn hir_crate_to_string(tcx: TyCtxt<'_>) -> String {
+    let hir_map = tcx.hir();
+    let root_module = hir_map.root_module();
+    let source_map = tcx.sess.source_map();
+    let root_file = source_map.lookup_source_file(root_module.spans.inner_span.lo());
+    let source = root_file.src.as_deref().cloned().unwrap_or_default();
+    let attrs = |id| hir_map.attrs(id);
+    let ann: &dyn Map<'_> = &hir_map;
+    rustc_hir_pretty::print_crate(
+        source_map,
+        root_module,
+        root_file.name.clone(),
+        source,
+        &attrs,
+        &ann,
+    )
+}
+
+/// Returns a HIR item as source code. The nested items and bodies are printed too, but not the
+/// attributes.
+pub fn hir_item_to_string(tcx: TyCtxt<'_>, item: &hir::Item<'_>) -> String {
+    let ann: &dyn Map<'_> = &tcx.hir();
+    rustc_hir_pretty::to_string(&ann, |state| state.print_item(item))
+}
+
+/// Returns a HIR expression as source code. The nested items and bodies (of closures for example)
+/// are printed too, but not the attributes.
+pub fn hir_expr_to_string(tcx: TyCtxt<'_>, expr: &hir::Expr<'_>) -> String {
+    let ann: &dyn Map<'_> = &tcx.hir();
+    rustc_hir_pretty::to_string(&ann, |state| state.print_expr(expr))
+}
+
+/// Returns a HIR type as source code.
+pub fn hir_ty_to_string(ty: &hir::Ty<'_>) -> String {
+    rustc_hir_pretty::ty_to_string(ty)
+}
@@ -0,0 +1,97 @@
+use rustc_data_structures::unord::UnordSet;
+use rustc_hir::def_id::LocalDefId;
+use rustc_interface::DEFAULT_QUERY_PROVIDERS;
+use rustc_middle::util::Providers;
+use rustc_session::Session;
+use rustc_span::symbol::{Ident, Symbol};
+
+use std::cell::RefCell;
+use std::sync::LazyLock;
+
+/// Function overriding rustc query providers, provided with
+/// [`AnalysisConfig::override_queries`](crate::AnalysisConfig::override_queries).
+pub type QueryOverride = fn(&Session, &mut Providers);
+
+/// Query overrides of the compilation running on the current thread.
+#[derive(Clone, Default)]
+pub(crate) struct QueryOverrides {
+    /// If `true`, the queries requiring the bodies to be type checked are disabled.
+    pub(crate) disable_typeck: bool,
+    pub(crate) user: Vec<QueryOverride>,
+    /// Tools added to the ones registered with `#![register_tool(...)]`, so their lints can be
+    /// used in the lint attributes and on the command line.
+    pub(crate) tools: Vec<String>,
+}
+
+thread_local! {
+    static QUERY_OVERRIDES: RefCell<QueryOverrides> = RefCell::new(QueryOverrides::default());
+}
+
+impl QueryOverrides {
+    /// `override_queries` is a function pointer so it cannot capture anything. Instead, the
+    /// overrides are stored in a thread local. It needs to be called in the compiler thread
+    /// before the queries are created, so from the `parse_sess_created` callback.
+    pub(crate) fn install(self) {
+        QUERY_OVERRIDES.with(|overrides| *overrides.borrow_mut() = self);
+    }
+}
+
+/// Resets the query overrides of the current thread when dropped, so the ones of a compilation
+/// don't leak into the next one running on the same thread. It needs to be dropped in the
+/// compiler thread once the compilation is over.
+pub(crate) struct QueryOverridesGuard;
+
+impl Drop for QueryOverridesGuard {
+    fn drop(&mut self) {
+        // The thread local may already be destroyed if the guard is dropped with the thread.
+        let _ = QUERY_OVERRIDES.try_with(|overrides| overrides.take());
+    }
+}
+
+/// Registers `tools` for the compilation running on the current thread. It needs to be called
+/// after [`QueryOverrides::install`] and before the global context is created, so from the
+/// `register_lints` callback.
+pub(crate) fn register_tools<'a>(tools: impl Iterator<Item = &'a str>) {
+    QUERY_OVERRIDES.with(|overrides| {
+        let registered = &mut overrides.borrow_mut().tools;
+        for tool in tools {
+            if !registered.iter().any(|registered| registered == tool) {
+                registered.push(tool.to_owned());
+            }
+        }
+    });
+}
+
+/// Used as `override_queries` in the compiler configuration. It applies the overrides installed
+/// with [`QueryOverrides::install`].
+pub(crate) fn override_queries(sess: &Session, providers: &mut Providers) {
+    let overrides = QUERY_OVERRIDES.with(|overrides| overrides.borrow().clone());
+    if overrides.disable_typeck {
+        // Most lints will require typechecking, so just don't run them.
+        providers.lint_mod = |_, _| {};
+        // hack so that `used_trait_imports` won't try to call typeck
+        providers.used_trait_imports = |_, _| {
+            static EMPTY_SET: LazyLock<UnordSet<LocalDefId>> = LazyLock::new(UnordSet::default);
+            &EMPTY_SET
+        };
+    }
+    if !overrides.tools.is_empty() {
+        providers.registered_tools = |tcx, ()| {
+            let mut tools = (DEFAULT_QUERY_PROVIDERS.registered_tools)(tcx, ());
+            QUERY_OVERRIDES.with(|overrides| {
+                tools.extend(
+                    overrides
+                        .borrow()
+                        .tools
+                        .iter()
+                        .map(|tool| Ident::with_dummy_span(Symbol::intern(tool))),
+                )
+            });
+            tools
+        };
+    }
+    // The user overrides come last so they can replace ours.
+    for user_override in overrides.user {
+        user_override(sess, providers);
+    }
+}
@@ -0,0 +1,116 @@
+use rustc_ast::ast::{AttrStyle, AttrVec, Item, ModSpans};
+use rustc_ast::attr;
+use rustc_ast::ptr::P;
+use rustc_ast::token::{self, Delimiter};
+use rustc_parse::parser::attr::InnerAttrPolicy;
+use rustc_parse::parser::{ForceCollect, Parser};
+use rustc_span::fatal_error::FatalErrorMarker;
+use thin_vec::ThinVec;
+
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+use crate::ast::expected;
+
+/// Creates a parser with `new_parser`. If the lexer fails (because of an unclosed delimiter for
+/// example), its errors are emitted and `None` is returned instead of aborting.
+pub(crate) fn new_parser_recovering<'a>(
+    new_parser: impl FnOnce() -> Parser<'a>,
+) -> Option<Parser<'a>> {
+    match catch_unwind(AssertUnwindSafe(new_parser)) {
+        Ok(parser) => Some(parser),
+        Err(payload) if payload.is::<FatalErrorMarker>() => None,
+        Err(payload) => resume_unwind(payload),
+    }
+}
+
+/// Same as `Parser::parse_mod(&token::Eof)`, but the errors are emitted instead of being returned.
+/// If an item cannot be parsed, it is skipped and the parser continues with the next one.
+pub(crate) fn parse_mod_recovering(
+    parser: &mut Parser<'_>,
+) -> (AttrVec, ThinVec<P<Item>>, ModSpans) {
+    let lo = parser.token.span;
+    let attrs = parse_inner_attributes_recovering(parser);
+
+    let post_attr_lo = parser.token.span;
+    let mut items = ThinVec::new();
+    while parser.token != token::Eof {
+        let snapshot = parser.clone();
+        match parser.parse_item(ForceCollect::No) {
+            Ok(Some(item)) => {
+                items.push(item);
+                continue;
+            }
+            Ok(None) => {
+                *parser = snapshot;
+                expected(parser, "an item").emit();
+            }
+            Err(mut db) => {
+                db.emit();
+                *parser = snapshot;
+            }
+        }
+        skip_item(parser);
+    }
+
+    let mod_spans = ModSpans {
+        inner_span: lo.to(parser.prev_token.span),
+        inject_use_span: post_attr_lo.data().with_hi(post_attr_lo.lo()),
+    };
+    (attrs, items, mod_spans)
+}
+
+/// Same as `Parser::parse_inner_attributes` (which isn't public), but stops at the first invalid
+/// attribute. The tokens which are left will be skipped by `parse_mod_recovering`.
+fn parse_inner_attributes_recovering(parser: &mut Parser<'_>) -> AttrVec {
+    let mut attrs = AttrVec::new();
+    loop {
+        if parser.token == token::Pound && parser.look_ahead(1, |t| t == &token::Not) {
+            let snapshot = parser.clone();
+            match parser.parse_attribute(InnerAttrPolicy::Permitted) {
+                Ok(attr) => attrs.push(attr),
+                Err(mut db) => {
+                    db.emit();
+                    *parser = snapshot;
+                    return attrs;
+                }
+            }
+        } else if let token::DocComment(comment_kind, AttrStyle::Inner, data) = parser.token.kind {
+            parser.bump();
+            attrs.push(attr::mk_doc_comment(
+                &parser.sess.attr_id_generator,
+                comment_kind,
+                AttrStyle::Inner,
+                data,
+                parser.prev_token.span,
+            ));
+        } else {
+            return attrs;
+        }
+    }
+}
+
+/// Skips the tokens until the end of the current item: a `;` or a `{ ... }` block which isn't
+/// nested in another delimiter. The parser always moves forward, unless it is at the end of the
+/// file.
+fn skip_item(parser: &mut Parser<'_>) {
+    let mut depth = 0usize;
+    loop {
+        match parser.token.kind {
+            token::Eof => return,
+            token::Semi if depth == 0 => {
+                parser.bump();
+                return;
+            }
+            token::OpenDelim(_) => depth += 1,
+            token::CloseDelim(delimiter) => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && delimiter == Delimiter::Brace {
+                    parser.bump();
+                    return;
+                }
+            }
+            _ => {}
+        }
+        parser.bump();
+    }
+}
@@ -0,0 +1,365 @@
+use rustc_lint::{EarlyLintPass, LateLintPass, Lint, LintId, LintStore};
+use rustc_middle::ty::TyCtxt;
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::json::Json;
+
+type EarlyPassFactory = Arc<dyn Fn() -> Box<dyn EarlyLintPass> + Send + Sync>;
+type LatePassFactory =
+    Arc<dyn for<'tcx> Fn(TyCtxt<'tcx>) -> Box<dyn LateLintPass<'tcx> + 'tcx> + Send + Sync>;
+
+/// Documentation of a lint registered in a [`LintRegistry`]. The name, the default level and the
+/// description come from the `Lint` itself (as declared with `declare_tool_lint!`).
+#[derive(Clone, Debug)]
+pub struct LintInfo {
+    pub lint: &'static Lint,
+    /// Free name used to sort the lints in the catalog (like `style` or `perf`).
+    pub category: String,
+    /// Longer explanation of the lint (what it checks and why), in markdown.
+    pub explanation: String,
+    /// Code examples which trigger the lint.
+    pub examples: Vec<String>,
+}
+
+impl LintInfo {
+    pub fn new<S: Into<String>>(lint: &'static Lint, category: S) -> Self {
+        Self {
+            lint,
+            category: category.into(),
+            explanation: String::new(),
+            examples: Vec::new(),
+        }
+    }
+
+    pub fn explanation<S: Into<String>>(mut self, explanation: S) -> Self {
+        self.explanation = explanation.into();
+        self
+    }
+
+    pub fn example<S: Into<String>>(mut self, code: S) -> Self {
+        self.examples.push(code.into());
+        self
+    }
+
+    /// Name of the lint, in lowercase (like in the diagnostics and the lint attributes).
+    pub fn name(&self) -> String {
+        self.lint.name_lower()
+    }
+}
+
+/// Format of [`LintRegistry::catalog`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatalogFormat {
+    /// A table of the lints for each category, like `rustc -W help` (to implement a
+    /// `--list-lints` option for example).
+    Text,
+    /// A documentation page with the explanation and the examples of each lint.
+    Markdown,
+    /// An array of lints, with all their information.
+    Json,
+}
+
+/// Collects the lints of a tool with their documentation and their passes, so they can be
+/// registered in the `LintStore` at once and documented with [`LintRegistry::catalog`]:
+///
+/// ```ignore
+/// let registry = LintRegistry::new()
+///     .tool("mytool")
+///     .lint(LintInfo::new(WARN_GENERICS, "style").explanation("Generics are ugly."))
+///     .early_pass(|| Box::new(WarnGenerics));
+///
+/// if std::env::args().any(|arg| arg == "--list-lints") {
+///     print!("{}", registry.catalog(CatalogFormat::Text));
+///     return;
+/// }
+/// with_lints(&args, Vec::new(), DiagnosticOutput::Stderr, move |store| registry.register(store))
+/// ```
+///
+/// A `LintRegistry` can be cloned cheaply, the passes are shared.
+#[derive(Clone, Default)]
+pub struct LintRegistry {
+    tool: Option<String>,
+    lints: Vec<LintInfo>,
+    /// Groups added with [`LintRegistry::group`], without the tool name.
+    groups: Vec<(String, Vec<&'static Lint>)>,
+    early_passes: Vec<EarlyPassFactory>,
+    late_passes: Vec<LatePassFactory>,
+}
+
+impl LintRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tool name of the lints (the `mytool` of `declare_tool_lint!(pub mytool::LINT, ..)`).
+    /// Each category then becomes a lint group (`mytool::style` for example) and all the lints
+    /// are in the `mytool::all` group, so they can be used in the lint attributes
+    /// (`#[allow(mytool::style)]`) and on the command line (`-D mytool::all`).
+    ///
+    /// There is no need to add `#![register_tool(mytool)]` to the analyzed crate: the tools of
+    /// the lints registered in the [`with_lints`](crate::with_lints) callback are registered
+    /// automatically.
+    ///
+    /// If it isn't set, the tool name is the one of the lints. All the lints need to be declared
+    /// with this tool name, otherwise [`LintRegistry::register`] panics.
+    pub fn tool<S: Into<String>>(mut self, tool: S) -> Self {
+        self.tool = Some(tool.into());
+        self
+    }
+
+    /// Adds the lint group `name` (prefixed with the tool name, like `mytool::name`). If a group
+    /// with the same name already exists (like the one of a category), `lints` are added to it.
+    pub fn group<S: Into<String>>(mut self, name: S, lints: &[&'static Lint]) -> Self {
+        self.groups.push((name.into(), lints.to_vec()));
+        self
+    }
+
+    /// Adds a lint. It needs to be emitted by one of the passes of the registry.
+    pub fn lint(mut self, info: LintInfo) -> Self {
+        self.lints.push(info);
+        self
+    }
+
+    /// Adds an early lint pass (which implements `EarlyLintPass`). The function is called to
+    /// create the pass for each compilation.
+    pub fn early_pass<F: Fn() -> Box<dyn EarlyLintPass> + Send + Sync + 'static>(
+        mut self,
+        pass: F,
+    ) -> Self {
+        self.early_passes.push(Arc::new(pass));
+        self
+    }
+
+    /// Adds a late lint pass (which implements `LateLintPass`). The function is called to create
+    /// the pass for each compilation.
+    pub fn late_pass<
+        F: for<'tcx> Fn(TyCtxt<'tcx>) -> Box<dyn LateLintPass<'tcx> + 'tcx> + Send + Sync + 'static,
+    >(
+        mut self,
+        pass: F,
+    ) -> Self {
+        self.late_passes.push(Arc::new(pass));
+        self
+    }
+
+    /// Returns the lints of the registry, in the order they were added.
+    pub fn lints(&self) -> &[LintInfo] {
+        &self.lints
+    }
+
+    /// Returns the tool name set with [`LintRegistry::tool`], or the one of the first lint.
+    fn tool_name(&self) -> Option<&str> {
+        self.tool
+            .as_deref()
+            .or_else(|| self.lints.first().and_then(|info| lint_tool(info.lint)))
+    }
+
+    /// Returns the lint groups with their full name: the categories (if the lints have a tool
+    /// name), the groups added with [`LintRegistry::group`] and the `all` group.
+    pub fn groups(&self) -> Vec<(String, Vec<&'static Lint>)> {
+        let tool = self.tool_name();
+        let mut groups: Vec<(String, Vec<&'static Lint>)> = Vec::new();
+        let mut add = |name: &str, lint: &'static Lint| {
+            let name = match tool {
+                Some(tool) => format!("{}::{}", tool, name),
+                None => name.to_owned(),
+            };
+            match groups.iter_mut().find(|(group, _)| *group == name) {
+                Some((_, lints)) if lints.iter().any(|other| other.name == lint.name) => {}
+                Some((_, lints)) => lints.push(lint),
+                None => groups.push((name, vec![lint])),
+            }
+        };
+        if tool.is_some() {
+            for info in &self.lints {
+                add(&info.category, info.lint);
+            }
+        }
+        for (name, lints) in &self.groups {
+            for lint in lints {
+                add(name, lint);
+            }
+        }
+        if tool.is_some() {
+            for info in &self.lints {
+                add("all", info.lint);
+            }
+        }
+        groups
+    }
+
+    /// Registers the lints, the groups and the passes into `lint_store`. Call it from the
+    /// [`with_lints`](crate::with_lints) callback.
+    ///
+    /// Panics if a lint hasn't been declared with the tool name of the registry (take a look at
+    /// [`LintRegistry::tool`]).
+    pub fn register(&self, lint_store: &mut LintStore) {
+        let tool = self.tool_name();
+        for info in &self.lints {
+            if lint_tool(info.lint) == tool {
+                continue;
+            }
+            match tool {
+                Some(tool) => panic!(
+                    "the lint `{}` isn't declared with the `{}` tool name of the registry",
+                    info.lint.name, tool,
+                ),
+                None => panic!(
+                    "the lint `{}` is declared with a tool name, unlike the other lints of the \
+                     registry",
+                    info.lint.name,
+                ),
+            }
+        }
+        let lints = self.lints.iter().map(|info| info.lint).collect::<Vec<_>>();
+        lint_store.register_lints(&lints);
+        for (name, lints) in self.groups() {
+            // The `LintStore` only accepts static names. It's only leaked once per compilation.
+            let name = Box::leak(name.into_boxed_str());
+            let lints = lints.into_iter().map(LintId::of).collect();
+            lint_store.register_group(true, name, None, lints);
+        }
+        for pass in &self.early_passes {
+            let pass = Arc::clone(pass);
+            lint_store.register_early_pass(move || pass());
+        }
+        for pass in &self.late_passes {
+            let pass = Arc::clone(pass);
+            lint_store.register_late_pass(move |tcx| pass(tcx));
+        }
+    }
+
+    /// Returns the lints sorted by category (in the order the categories first appear), then by
+    /// name.
+    fn sorted_lints(&self) -> Vec<&LintInfo> {
+        let mut categories = Vec::new();
+        for info in &self.lints {
+            if !categories.contains(&info.category.as_str()) {
+                categories.push(&info.category);
+            }
+        }
+        let mut lints = self.lints.iter().collect::<Vec<_>>();
+        lints.sort_by_key(|info| {
+            let category = categories
+                .iter()
+                .position(|category| *category == info.category);
+            (category, info.lint.name)
+        });
+        lints
+    }
+
+    /// Returns the documentation of all the lints of the registry.
+    pub fn catalog(&self, format: CatalogFormat) -> String {
+        let lints = self.sorted_lints();
+        match format {
+            CatalogFormat::Text => text_catalog(&lints, &self.groups()),
+            CatalogFormat::Markdown => markdown_catalog(&lints),
+            CatalogFormat::Json => Json::Array(
+                lints
+                    .iter()
+                    .map(|info| {
+                        Json::Object(vec![
+                            ("name", Json::String(info.name())),
+                            ("category", Json::from(info.category.as_str())),
+                            (
+                                "default_level",
+                                Json::from(info.lint.default_level.as_str()),
+                            ),
+                            ("description", Json::from(info.lint.desc)),
+                            ("explanation", Json::from(info.explanation.as_str())),
+                            (
+                                "examples",
+                                Json::Array(
+                                    info.examples
+                                        .iter()
+                                        .map(|example| Json::from(example.as_str()))
+                                        .collect(),
+                                ),
+                            ),
+                        ])
+                    })
+                    .collect(),
+            )
+            .to_string(),
+        }
+    }
+}
+
+/// Returns the tool name of a lint declared with `declare_tool_lint!`.
+fn lint_tool(lint: &Lint) -> Option<&'static str> {
+    lint.name.split_once("::").map(|(tool, _)| tool)
+}
+
+fn text_catalog(lints: &[&LintInfo], groups: &[(String, Vec<&'static Lint>)]) -> String {
+    let names = lints.iter().map(|info| info.name()).collect::<Vec<_>>();
+    let width = names.iter().map(String::len).max().unwrap_or(0).max(4);
+    let mut output = String::new();
+    let mut category = None;
+    for (info, name) in lints.iter().zip(&names) {
+        if category != Some(info.category.as_str()) {
+            if category.is_some() {
+                output.push('\n');
+            }
+            category = Some(info.category.as_str());
+            writeln!(output, "{}:", info.category.as_str()).unwrap();
+            writeln!(output, "    {:width$}  default  meaning", "name").unwrap();
+            writeln!(output, "    {:width$}  -------  -------", "----").unwrap();
+        }
+        writeln!(
+            output,
+            "    {:width$}  {:7}  {}",
+            name,
+            info.lint.default_level.as_str(),
+            info.lint.desc,
+        )
+        .unwrap();
+    }
+
+    if !groups.is_empty() {
+        let width = groups
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        writeln!(output, "\nLint groups:").unwrap();
+        writeln!(output, "    {:width$}  sub-lints", "name").unwrap();
+        writeln!(output, "    {:width$}  ---------", "----").unwrap();
+        for (name, lints) in groups {
+            let lints = lints
+                .iter()
+                .map(|lint| lint.name_lower())
+                .collect::<Vec<_>>();
+            writeln!(output, "    {:width$}  {}", name, lints.join(", ")).unwrap();
+        }
+    }
+    output
+}
+
+fn markdown_catalog(lints: &[&LintInfo]) -> String {
+    let mut output = String::from("# Lints\n");
+    let mut category = None;
+    for info in lints {
+        if category != Some(info.category.as_str()) {
+            category = Some(info.category.as_str());
+            write!(output, "\n## {}\n", info.category.as_str()).unwrap();
+        }
+        write!(
+            output,
+            "\n### `{}`\n\nDefault level: `{}`\n\n{}\n",
+            info.name(),
+            info.lint.default_level.as_str(),
+            info.lint.desc,
+        )
+        .unwrap();
+        if !info.explanation.is_empty() {
+            write!(output, "\n{}\n", info.explanation.trim_end()).unwrap();
+        }
+        for example in &info.examples {
+            write!(output, "\n```rust\n{}\n```\n", example.trim_end()).unwrap();
+        }
+    }
+    output
+}
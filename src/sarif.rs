@@ -0,0 +1,417 @@
+use rustc_lint::LintStore;
+use rustc_lint_defs::Level;
+use rustc_span::edition::Edition;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::diagnostics::{
+    CapturedDiagnostic, CapturedSpan, DiagnosticCode, DiagnosticCollector, DiagnosticLevel,
+};
+use crate::json::Json;
+
+/// Base of the relative paths of the log: the directory in which the compiler has been run.
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Metadata of a lint registered in the `LintStore`.
+#[derive(Clone, Debug)]
+struct Rule {
+    description: &'static str,
+    default_level: Level,
+    /// `true` if the lint has been declared with `declare_tool_lint!`.
+    is_tool_lint: bool,
+}
+
+/// Stores the diagnostics emitted when using [`DiagnosticOutput::Sarif`], along with the metadata
+/// of the lints registered in the `LintStore`, so they can be converted into a
+/// [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) log with
+/// [`SarifCollector::to_sarif`]. It can be cloned freely, all clones share the same storage.
+///
+/// [`DiagnosticOutput::Sarif`]: crate::DiagnosticOutput::Sarif
+#[derive(Clone)]
+pub struct SarifCollector {
+    tool_name: String,
+    pub(crate) diagnostics: DiagnosticCollector,
+    /// Indexed by lint name (in lowercase, like in the diagnostics).
+    rules: Arc<Mutex<BTreeMap<String, Rule>>>,
+}
+
+impl SarifCollector {
+    /// `tool_name` is the name of the tool displayed in the SARIF log (usually the name of your
+    /// binary).
+    pub fn new<S: Into<String>>(tool_name: S) -> Self {
+        Self {
+            tool_name: tool_name.into(),
+            diagnostics: DiagnosticCollector::new(),
+            rules: Arc::default(),
+        }
+    }
+
+    /// Returns a copy of all the diagnostics captured so far.
+    pub fn diagnostics(&self) -> Vec<CapturedDiagnostic> {
+        self.diagnostics.diagnostics()
+    }
+
+    /// Returns a collector sharing the lints metadata of this one, but which stores its
+    /// diagnostics into `diagnostics`.
+    pub(crate) fn with_diagnostics(&self, diagnostics: DiagnosticCollector) -> Self {
+        Self {
+            tool_name: self.tool_name.clone(),
+            diagnostics,
+            rules: Arc::clone(&self.rules),
+        }
+    }
+
+    pub(crate) fn register_rules(&self, lint_store: &LintStore, edition: Edition) {
+        let mut rules = self.rules.lock().unwrap();
+        for lint in lint_store.get_lints() {
+            rules.insert(
+                lint.name_lower(),
+                Rule {
+                    description: lint.desc,
+                    default_level: lint.default_level(edition),
+                    is_tool_lint: lint.is_plugin,
+                },
+            );
+        }
+    }
+
+    /// Returns the SARIF log (in JSON) of the diagnostics captured so far.
+    ///
+    /// The rules of the log are the lints declared with `declare_tool_lint!`, the other lints and
+    /// the error codes are only added if a diagnostic uses them. The diagnostics which aren't
+    /// about the code (like "aborting due to previous error") are skipped. The relative paths of
+    /// the files are relative to the `%SRCROOT%` base URI, which is the current directory.
+    pub fn to_sarif(&self) -> String {
+        let diagnostics = self
+            .diagnostics
+            .diagnostics()
+            .into_iter()
+            .filter(|diagnostic| {
+                diagnostic.level != DiagnosticLevel::FailureNote
+                    && (!diagnostic.spans.is_empty()
+                        || diagnostic.code.is_some()
+                        || diagnostic.is_error())
+            })
+            .collect::<Vec<_>>();
+        let known_rules = self.rules.lock().unwrap();
+
+        let mut rule_ids = known_rules
+            .iter()
+            .filter(|(_, rule)| rule.is_tool_lint)
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        for diagnostic in &diagnostics {
+            if let Some(id) = diagnostic.code.as_ref().map(code_id) {
+                if !rule_ids.contains(&id) {
+                    rule_ids.push(id);
+                }
+            }
+        }
+        let rules = rule_ids
+            .iter()
+            .map(|id| {
+                let mut rule = vec![("id", Json::from(*id))];
+                if let Some(known) = known_rules.get(*id) {
+                    rule.push(("shortDescription", text(known.description)));
+                    rule.push((
+                        "defaultConfiguration",
+                        Json::Object(vec![("level", Json::from(lint_level(known.default_level)))]),
+                    ));
+                }
+                Json::Object(rule)
+            })
+            .collect();
+
+        let results = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let mut message = diagnostic.message.clone();
+                for child in &diagnostic.children {
+                    write!(message, "\n{}: {}", child.level.as_str(), child.message).unwrap();
+                }
+                let mut result = Vec::new();
+                if let Some(id) = diagnostic.code.as_ref().map(code_id) {
+                    let index = rule_ids.iter().position(|rule_id| *rule_id == id).unwrap();
+                    result.push(("ruleId", Json::from(id)));
+                    result.push(("ruleIndex", Json::Number(index)));
+                }
+                result.push(("level", Json::from(result_level(diagnostic.level))));
+                result.push(("message", text(&message)));
+                let (primary, secondary) = diagnostic
+                    .spans
+                    .iter()
+                    .partition::<Vec<_>, _>(|span| span.is_primary);
+                result.push(("locations", location_list(&primary)));
+                if !secondary.is_empty() {
+                    result.push(("relatedLocations", location_list(&secondary)));
+                }
+                Json::Object(result)
+            })
+            .collect();
+
+        let driver = Json::Object(vec![
+            ("name", Json::from(self.tool_name.as_str())),
+            ("rules", Json::Array(rules)),
+        ]);
+        let mut run = vec![("tool", Json::Object(vec![("driver", driver)]))];
+        let has_relative_paths = diagnostics
+            .iter()
+            .flat_map(|diagnostic| &diagnostic.spans)
+            .any(|span| !Path::new(&span.file_name).is_absolute());
+        if has_relative_paths {
+            if let Ok(current_dir) = std::env::current_dir() {
+                let mut uri = file_uri(&current_dir.display().to_string());
+                if !uri.ends_with('/') {
+                    uri.push('/');
+                }
+                run.push((
+                    "originalUriBaseIds",
+                    Json::Object(vec![(
+                        SRCROOT,
+                        Json::Object(vec![("uri", Json::String(uri))]),
+                    )]),
+                ));
+            }
+        }
+        // The columns of `CapturedSpan` are in characters.
+        run.push(("columnKind", Json::from("unicodeCodePoints")));
+        run.push(("results", Json::Array(results)));
+        let run = Json::Object(run);
+        Json::Object(vec![
+            (
+                "$schema",
+                Json::from("https://json.schemastore.org/sarif-2.1.0.json"),
+            ),
+            ("version", Json::from("2.1.0")),
+            ("runs", Json::Array(vec![run])),
+        ])
+        .to_string()
+    }
+}
+
+fn code_id(code: &DiagnosticCode) -> &str {
+    match code {
+        DiagnosticCode::Error(code) | DiagnosticCode::Lint(code) => code,
+    }
+}
+
+fn lint_level(level: Level) -> &'static str {
+    match level {
+        Level::Allow | Level::Expect(_) => "none",
+        Level::Warn | Level::ForceWarn(_) => "warning",
+        Level::Deny | Level::Forbid => "error",
+    }
+}
+
+fn result_level(level: DiagnosticLevel) -> &'static str {
+    match level {
+        DiagnosticLevel::Bug | DiagnosticLevel::Fatal | DiagnosticLevel::Error => "error",
+        DiagnosticLevel::Warning => "warning",
+        DiagnosticLevel::Note | DiagnosticLevel::Help | DiagnosticLevel::FailureNote => "note",
+    }
+}
+
+/// A SARIF `message` object.
+fn text(text: &str) -> Json {
+    Json::Object(vec![("text", Json::from(text))])
+}
+
+fn location_list(spans: &[&CapturedSpan]) -> Json {
+    Json::Array(spans.iter().map(|span| location(span)).collect())
+}
+
+fn location(span: &CapturedSpan) -> Json {
+    let region = Json::Object(vec![
+        ("startLine", Json::Number(span.line_start)),
+        ("startColumn", Json::Number(span.column_start)),
+        ("endLine", Json::Number(span.line_end)),
+        ("endColumn", Json::Number(span.column_end)),
+        ("byteOffset", Json::Number(span.byte_start as usize)),
+        (
+            "byteLength",
+            Json::Number(span.byte_end.saturating_sub(span.byte_start) as usize),
+        ),
+    ]);
+    let physical_location = Json::Object(vec![
+        ("artifactLocation", artifact_location(&span.file_name)),
+        ("region", region),
+    ]);
+    let mut location = vec![("physicalLocation", physical_location)];
+    if let Some(label) = &span.label {
+        location.push(("message", text(label)));
+    }
+    Json::Object(location)
+}
+
+/// A SARIF `artifactLocation` object. The relative paths are relative to [`SRCROOT`].
+fn artifact_location(file_name: &str) -> Json {
+    if Path::new(file_name).is_absolute() {
+        Json::Object(vec![("uri", Json::String(file_uri(file_name)))])
+    } else {
+        Json::Object(vec![
+            ("uri", Json::String(percent_encode(file_name))),
+            ("uriBaseId", Json::from(SRCROOT)),
+        ])
+    }
+}
+
+/// Returns the `file://` URI of an absolute path.
+fn file_uri(path: &str) -> String {
+    match path.as_bytes() {
+        // Windows paths start with the drive letter, whose `:` is kept.
+        [letter, b':', ..] if letter.is_ascii_alphabetic() => {
+            format!("file:///{}{}", &path[..2], percent_encode(&path[2..]))
+        }
+        _ => format!("file://{}", percent_encode(path)),
+    }
+}
+
+/// Percent-encodes a path so it can be used in a URI. The separators are replaced with `/`.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'\\' => encoded.push('/'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{CapturedSuggestion, CapturedSuggestionPart};
+    use rustc_errors::Applicability;
+
+    fn span(file_name: &str, is_primary: bool, label: Option<&str>) -> CapturedSpan {
+        CapturedSpan {
+            file_name: file_name.to_owned(),
+            local_path: None,
+            byte_start: 10,
+            byte_end: 13,
+            line_start: 2,
+            line_end: 2,
+            column_start: 4,
+            column_end: 7,
+            is_primary,
+            label: label.map(str::to_owned),
+        }
+    }
+
+    fn diagnostic(
+        level: DiagnosticLevel,
+        message: &str,
+        spans: Vec<CapturedSpan>,
+    ) -> CapturedDiagnostic {
+        CapturedDiagnostic {
+            level,
+            message: message.to_owned(),
+            code: None,
+            spans,
+            children: Vec::new(),
+            suggestions: Vec::new(),
+            rendered: String::new(),
+        }
+    }
+
+    #[test]
+    fn golden() {
+        let sarif = SarifCollector::new("tool");
+        sarif.rules.lock().unwrap().insert(
+            "tool::bad_name".to_owned(),
+            Rule {
+                description: "checks the \"names\"",
+                default_level: Level::Warn,
+                is_tool_lint: true,
+            },
+        );
+        let mut lint = diagnostic(
+            DiagnosticLevel::Warning,
+            "bad name `é`",
+            vec![
+                span("/src/lib.rs", true, Some("here")),
+                span("/src/main.rs", false, None),
+            ],
+        );
+        lint.code = Some(DiagnosticCode::Lint("tool::bad_name".to_owned()));
+        lint.children
+            .push(diagnostic(DiagnosticLevel::Help, "rename it", Vec::new()));
+        lint.suggestions.push(CapturedSuggestion {
+            message: "rename it".to_owned(),
+            applicability: Applicability::MachineApplicable,
+            parts: vec![CapturedSuggestionPart {
+                span: span("/src/lib.rs", true, None),
+                replacement: "good_name".to_owned(),
+            }],
+        });
+        sarif.diagnostics.push(lint);
+        let mut error = diagnostic(DiagnosticLevel::Error, "mismatched types", Vec::new());
+        error.code = Some(DiagnosticCode::Error("E0308".to_owned()));
+        sarif.diagnostics.push(error);
+        // Skipped.
+        sarif.diagnostics.push(diagnostic(
+            DiagnosticLevel::FailureNote,
+            "For more information about this error, try `rustc --explain E0308`.",
+            Vec::new(),
+        ));
+
+        assert_eq!(
+            sarif.to_sarif(),
+            concat!(
+                r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","runs":[{"#,
+                r#""tool":{"driver":{"name":"tool","rules":["#,
+                r#"{"id":"tool::bad_name","shortDescription":{"text":"checks the \"names\""},"#,
+                r#""defaultConfiguration":{"level":"warning"}},{"id":"E0308"}]}},"#,
+                r#""columnKind":"unicodeCodePoints","results":["#,
+                r#"{"ruleId":"tool::bad_name","ruleIndex":0,"level":"warning","#,
+                r#""message":{"text":"bad name `é`\nhelp: rename it"},"#,
+                r#""locations":[{"physicalLocation":{"artifactLocation":{"uri":"file:///src/lib.rs"},"#,
+                r#""region":{"startLine":2,"startColumn":4,"endLine":2,"endColumn":7,"byteOffset":10,"byteLength":3}},"#,
+                r#""message":{"text":"here"}}],"#,
+                r#""relatedLocations":[{"physicalLocation":{"artifactLocation":{"uri":"file:///src/main.rs"},"#,
+                r#""region":{"startLine":2,"startColumn":4,"endLine":2,"endColumn":7,"byteOffset":10,"byteLength":3}}}]},"#,
+                r#"{"ruleId":"E0308","ruleIndex":1,"level":"error","message":{"text":"mismatched types"},"locations":[]}"#,
+                r#"]}]}"#,
+            ),
+        );
+    }
+
+    #[test]
+    fn relative_paths() {
+        let sarif = SarifCollector::new("tool");
+        sarif.diagnostics.push(diagnostic(
+            DiagnosticLevel::Warning,
+            "unused",
+            vec![span("src/a b.rs", true, None)],
+        ));
+        let output = sarif.to_sarif();
+        assert!(
+            output.contains(r#""artifactLocation":{"uri":"src/a%20b.rs","uriBaseId":"%SRCROOT%"}"#)
+        );
+        let mut src_root = file_uri(&std::env::current_dir().unwrap().display().to_string());
+        if !src_root.ends_with('/') {
+            src_root.push('/');
+        }
+        assert!(output.contains(&format!(
+            r#""originalUriBaseIds":{{"%SRCROOT%":{{"uri":"{src_root}"}}}}"#
+        )));
+    }
+
+    #[test]
+    fn uris() {
+        assert_eq!(
+            percent_encode("dir with space/100%#1.rs"),
+            "dir%20with%20space/100%25%231.rs",
+        );
+        assert_eq!(percent_encode("src\\é.rs"), "src/%C3%A9.rs");
+        assert_eq!(file_uri("/a b/c#d.rs"), "file:///a%20b/c%23d.rs");
+        assert_eq!(file_uri("C:\\a b\\c.rs"), "file:///C:/a%20b/c.rs");
+    }
+}
@@ -0,0 +1,737 @@
+//! Helpers to test the tools built with this crate.
+//!
+//! [`UiTests`] runs lints over fixture files and compares their diagnostics with `.stderr`
+//! snapshot files and with the `//~` annotations of the fixtures, like the rustc UI tests.
+//!
+//! [`with_ast_snippet`] and [`with_tyctxt_snippet`] run an analysis over a code snippet and
+//! return its result along with the diagnostics, so it can be tested without any file.
+
+use rustc_ast::ast::Crate;
+use rustc_lint::LintStore;
+use rustc_middle::ty::TyCtxt;
+use rustc_session::parse::ParseSess;
+use rustc_span::edition::Edition;
+
+use std::convert::Infallible;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::ast::with_ast_parser_config;
+use crate::config::{AnalysisConfig, ParseConfig};
+use crate::diagnostics::{
+    CapturedDiagnostic, DiagnosticCollector, DiagnosticLevel, DiagnosticOutput,
+};
+use crate::hir::with_tyctxt_config;
+use crate::lint::with_lints_config;
+use crate::Error;
+
+/// Runs lints over the fixtures (`.rs` files) of a directory and checks their diagnostics (take
+/// a look at the `tests/ui.rs` file of this crate):
+///
+/// ```ignore
+/// #[test]
+/// fn ui() {
+///     UiTests::new("tests/ui")
+///         .bless(std::env::var_os("BLESS").is_some())
+///         .run(|store| registry.register(store))
+///         .assert_ok();
+/// }
+/// ```
+///
+/// For each fixture, the rendered diagnostics are compared with the file with the same name and
+/// the `.stderr` extension (a missing file means no diagnostics are expected). The directory of
+/// the fixtures is replaced with `$DIR` in the output so the snapshots don't depend on where the
+/// tests are run. In bless mode, the `.stderr` files are updated instead.
+///
+/// The fixtures can also be annotated with the expected diagnostics, at the end of the line
+/// where they're emitted:
+///
+/// ```text
+/// fn foo<T>(t: T) {} //~ WARN generics are ugly
+/// //~^ WARN generics are ugly
+/// ```
+///
+/// `//~^` points to the line above (`//~^^` two lines above, etc), and `//~|` to the same line
+/// as the previous annotation. The level is `ERROR`, `WARN`, `NOTE` or `HELP`, and the message
+/// (which is optional) needs to be a part of the diagnostic message. If a fixture has
+/// annotations, all the errors and warnings it emits need to be annotated too.
+///
+/// Extra rustc arguments can be added with [`UiTests::arg`] for all fixtures, or with a
+/// `//@ compile-flags: ...` line for one fixture. The fixtures are compiled as libraries unless
+/// `--crate-type` is one of these arguments, and only their metadata is written, in a temporary
+/// directory, unless the arguments set the outputs (`--emit`, `--out-dir` or `-o`).
+#[derive(Clone, Debug)]
+pub struct UiTests {
+    dir: PathBuf,
+    args: Vec<String>,
+    bless: bool,
+}
+
+/// Why a fixture of [`UiTests`] failed.
+#[derive(Clone, Debug)]
+pub struct UiTestFailure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for UiTestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Results of [`UiTests::run`].
+#[derive(Clone, Debug, Default)]
+pub struct UiTestReport {
+    pub passed: Vec<PathBuf>,
+    /// Fixtures whose `.stderr` file has been updated (only in bless mode).
+    pub blessed: Vec<PathBuf>,
+    pub failures: Vec<UiTestFailure>,
+}
+
+impl UiTestReport {
+    /// Panics with all the failures if there are any.
+    pub fn assert_ok(&self) {
+        if self.failures.is_empty() {
+            return;
+        }
+        let mut message = format!("{} UI test(s) failed:", self.failures.len());
+        for failure in &self.failures {
+            message.push_str(&format!("\n\n{}", failure));
+        }
+        panic!("{}", message);
+    }
+}
+
+impl UiTests {
+    /// `dir` is the directory containing the fixtures. Its subdirectories are included.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            args: Vec::new(),
+            bless: false,
+        }
+    }
+
+    /// Adds a rustc argument used for all the fixtures.
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// If `true`, the `.stderr` files are updated with the actual output instead of being
+    /// compared with it. The annotations are still checked.
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    /// Runs the lints registered by `callback` (like in [`with_lints`](crate::with_lints)) over
+    /// every fixture.
+    pub fn run<F: Fn(&mut LintStore) + Send + Sync + 'static>(&self, callback: F) -> UiTestReport {
+        let callback = Arc::new(callback);
+        let mut report = UiTestReport::default();
+        let mut fixtures = Vec::new();
+        if let Err(error) = collect_fixtures(&self.dir, &mut fixtures) {
+            report.failures.push(UiTestFailure {
+                path: self.dir.clone(),
+                message: format!("cannot read the fixtures: {}", error),
+            });
+        }
+        fixtures.sort();
+
+        for path in fixtures {
+            let callback = Arc::clone(&callback);
+            match self.run_fixture(&path, move |store: &mut LintStore| callback(store)) {
+                Ok(false) => report.passed.push(path),
+                Ok(true) => report.blessed.push(path),
+                Err(message) => report.failures.push(UiTestFailure { path, message }),
+            }
+        }
+        report
+    }
+
+    /// Returns `true` if the `.stderr` file has been updated.
+    fn run_fixture<F: Fn(&mut LintStore) + Send + Sync + 'static>(
+        &self,
+        path: &Path,
+        callback: F,
+    ) -> Result<bool, String> {
+        let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let collector = DiagnosticCollector::new();
+        let compile_flags = source
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("//@"))
+            .filter_map(|header| header.trim().strip_prefix("compile-flags:"))
+            .flat_map(str::split_whitespace);
+        let args = self
+            .args
+            .iter()
+            .map(String::as_str)
+            .chain(compile_flags)
+            .collect::<Vec<_>>();
+        let mut config = AnalysisConfig::new(path)
+            .diagnostic_output(DiagnosticOutput::Capture(collector.clone()));
+        if !args.iter().any(|arg| arg.starts_with("--crate-type")) {
+            config = config.arg("--crate-type=lib");
+        }
+        for arg in args {
+            config = config.arg(arg);
+        }
+        let output_dir = OutputDir::new();
+        let config = output_dir.apply(config);
+
+        match with_lints_config(&config, callback) {
+            Ok(()) | Err(Error::Compilation(_)) => {}
+            Err(error) => return Err(format!("the compiler failed: {}", error)),
+        }
+        let diagnostics = collector.take();
+
+        let mut errors = check_annotations(path, &source, &diagnostics);
+        let actual = normalize_output(&self.dir, &diagnostics);
+        let stderr_path = path.with_extension("stderr");
+        let expected = fs::read_to_string(&stderr_path).unwrap_or_default();
+        let mut blessed = false;
+        if actual != expected {
+            if self.bless {
+                let result = if actual.is_empty() {
+                    fs::remove_file(&stderr_path)
+                } else {
+                    fs::write(&stderr_path, &actual)
+                };
+                result.map_err(|error| {
+                    format!("cannot update `{}`: {}", stderr_path.display(), error)
+                })?;
+                blessed = true;
+            } else {
+                errors.push(format!(
+                    "the output doesn't match `{}` (run in bless mode to update it):\n{}",
+                    stderr_path.display(),
+                    line_diff(&expected, &actual),
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(blessed)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+fn collect_fixtures(dir: &Path, fixtures: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_fixtures(&path, fixtures)?;
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            fixtures.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the rendered diagnostics, with `dir` replaced with `$DIR`.
+fn normalize_output(dir: &Path, diagnostics: &[CapturedDiagnostic]) -> String {
+    let output = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.rendered.as_str())
+        .collect::<String>();
+    let mut dirs = vec![dir.display().to_string()];
+    if let Ok(canonical) = dir.canonicalize() {
+        dirs.push(canonical.display().to_string());
+    }
+    // The longest paths first, so a path containing another one is replaced completely.
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.len()));
+    dirs.into_iter()
+        .filter(|dir| !dir.is_empty())
+        .fold(output, |output, dir| replace_dir(&output, &dir))
+}
+
+/// Replaces `dir` with `$DIR` in `output`. The separators of the rest of the paths starting with
+/// `dir` are replaced with `/`, so the output is the same on Windows. The other backslashes are
+/// kept as is (they can be part of the messages).
+fn replace_dir(output: &str, dir: &str) -> String {
+    let mut result = String::with_capacity(output.len());
+    let mut rest = output;
+    while let Some(index) = rest.find(dir) {
+        result.push_str(&rest[..index]);
+        result.push_str("$DIR");
+        rest = &rest[index + dir.len()..];
+        let path_end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, ':' | '`' | '\'' | '"' | ')'))
+            .unwrap_or(rest.len());
+        result.push_str(&rest[..path_end].replace('\\', "/"));
+        rest = &rest[path_end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// A diagnostic expected with a `//~` annotation, or emitted by the compiler.
+#[derive(Debug)]
+struct Report {
+    line: usize,
+    level: &'static str,
+    message: String,
+}
+
+fn annotation_level(level: DiagnosticLevel) -> &'static str {
+    match level {
+        DiagnosticLevel::Bug | DiagnosticLevel::Fatal | DiagnosticLevel::Error => "ERROR",
+        DiagnosticLevel::Warning => "WARN",
+        DiagnosticLevel::Note | DiagnosticLevel::FailureNote => "NOTE",
+        DiagnosticLevel::Help => "HELP",
+    }
+}
+
+/// Parses the `//~` annotations of `source`.
+fn parse_annotations(source: &str) -> Result<Vec<Report>, String> {
+    let mut annotations: Vec<Report> = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let Some((_, annotation)) = line.split_once("//~") else {
+            continue;
+        };
+        let line_number = index + 1;
+        let (target, annotation) = if let Some(annotation) = annotation.strip_prefix('|') {
+            match annotations.last() {
+                Some(previous) => (previous.line, annotation),
+                None => {
+                    return Err(format!(
+                        "line {}: `//~|` without a previous annotation",
+                        line_number
+                    ))
+                }
+            }
+        } else {
+            let above = annotation.len() - annotation.trim_start_matches('^').len();
+            if above >= line_number {
+                return Err(format!(
+                    "line {}: the annotation points before the file",
+                    line_number
+                ));
+            }
+            (line_number - above, &annotation[above..])
+        };
+        let annotation = annotation.trim_start();
+        let (level, message) = annotation
+            .split_once(char::is_whitespace)
+            .unwrap_or((annotation, ""));
+        let level = match level {
+            "ERROR" => "ERROR",
+            "WARN" | "WARNING" => "WARN",
+            "NOTE" => "NOTE",
+            "HELP" => "HELP",
+            _ => {
+                return Err(format!(
+                    "line {}: unknown level `{}` (expected `ERROR`, `WARN`, `NOTE` or `HELP`)",
+                    line_number, level
+                ))
+            }
+        };
+        annotations.push(Report {
+            line: target,
+            level,
+            message: message.trim().to_owned(),
+        });
+    }
+    Ok(annotations)
+}
+
+/// Returns the diagnostics (and their notes and helps) emitted in the file at `path`.
+fn emitted_reports(path: &Path, diagnostics: &[CapturedDiagnostic]) -> Vec<Report> {
+    let file_name = path.display().to_string();
+    let line_in_file = |diagnostic: &CapturedDiagnostic| {
+        diagnostic
+            .primary_span()
+            .filter(|span| span.file_name == file_name)
+            .map(|span| span.line_start)
+    };
+    let mut reports = Vec::new();
+    for diagnostic in diagnostics {
+        let Some(line) = line_in_file(diagnostic) else {
+            continue;
+        };
+        reports.push(Report {
+            line,
+            level: annotation_level(diagnostic.level),
+            message: diagnostic.message.clone(),
+        });
+        for child in &diagnostic.children {
+            reports.push(Report {
+                line: line_in_file(child).unwrap_or(line),
+                level: annotation_level(child.level),
+                message: child.message.clone(),
+            });
+        }
+    }
+    reports
+}
+
+/// Checks that the annotations of the fixture match the emitted diagnostics. Returns the errors.
+fn check_annotations(path: &Path, source: &str, diagnostics: &[CapturedDiagnostic]) -> Vec<String> {
+    let annotations = match parse_annotations(source) {
+        Ok(annotations) => annotations,
+        Err(error) => return vec![error],
+    };
+    if annotations.is_empty() {
+        return Vec::new();
+    }
+    let mut emitted = emitted_reports(path, diagnostics)
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    let mut errors = Vec::new();
+    for annotation in &annotations {
+        let matching = emitted.iter_mut().find(|report| {
+            report.as_ref().map_or(false, |report| {
+                report.line == annotation.line
+                    && report.level == annotation.level
+                    && report.message.contains(&annotation.message)
+            })
+        });
+        match matching {
+            Some(report) => *report = None,
+            None => errors.push(format!(
+                "line {}: expected {} `{}` wasn't emitted",
+                annotation.line, annotation.level, annotation.message
+            )),
+        }
+    }
+    for report in emitted.into_iter().flatten() {
+        if matches!(report.level, "ERROR" | "WARN") {
+            errors.push(format!(
+                "line {}: unexpected {} `{}`",
+                report.line, report.level, report.message
+            ));
+        }
+    }
+    errors
+}
+
+/// Returns the lines which differ between `expected` and `actual` (prefixed with `-` and `+`).
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    // Longest common subsequence of the lines, from the end.
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            output.push_str(&format!(" {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j < actual.len()
+            && (i == expected.len() || lengths[i][j + 1] >= lengths[i + 1][j])
+        {
+            output.push_str(&format!("+{}\n", actual[j]));
+            j += 1;
+        } else {
+            output.push_str(&format!("-{}\n", expected[i]));
+            i += 1;
+        }
+    }
+    output
+}
+
+/// File name of the snippets analyzed by [`with_ast_snippet`] and [`with_tyctxt_snippet`], as
+/// displayed in the diagnostics.
+pub const SNIPPET_FILE_NAME: &str = "snippet.rs";
+
+/// Result of an analysis run over a code snippet, along with the diagnostics emitted while
+/// running it.
+#[derive(Debug)]
+pub struct SnippetOutput<T, E = Infallible> {
+    /// The value returned by the callback, or why it failed (or couldn't be called).
+    pub result: Result<T, Error<E>>,
+    pub diagnostics: Vec<CapturedDiagnostic>,
+}
+
+impl<T, E> SnippetOutput<T, E> {
+    fn new(result: Result<T, Error<E>>, collector: DiagnosticCollector) -> Self {
+        let mut diagnostics = collector.take();
+        // The errors which stopped the analysis aren't always emitted in the output.
+        if let Err(Error::Parser(errors) | Error::Compilation(errors)) = &result {
+            for error in errors {
+                if !diagnostics.contains(error) {
+                    diagnostics.push(error.clone());
+                }
+            }
+        }
+        Self {
+            result,
+            diagnostics,
+        }
+    }
+
+    /// Returns the diagnostics as rustc displays them, to compare them with a snapshot.
+    pub fn rendered_diagnostics(&self) -> String {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.rendered.as_str())
+            .collect()
+    }
+
+    /// Returns the value returned by the callback. Panics with the diagnostics if the analysis
+    /// failed.
+    pub fn unwrap(self) -> T
+    where
+        E: fmt::Display,
+    {
+        match self.result {
+            Ok(value) => value,
+            // The errors are already in the diagnostics.
+            Err(Error::Parser(_) | Error::Compilation(_)) => panic!(
+                "the analysis of the snippet failed:\n{}",
+                self.rendered_diagnostics()
+            ),
+            Err(ref error) => panic!(
+                "the analysis of the snippet failed: {}\n{}",
+                error,
+                self.rendered_diagnostics()
+            ),
+        }
+    }
+}
+
+/// Parses `source` (in the 2021 edition) and calls `callback` with its AST, like
+/// [`with_ast_parser`](crate::with_ast_parser). The diagnostics are captured instead of being
+/// printed:
+///
+/// ```ignore
+/// let output = with_ast_snippet("fn foo() {}", |_, krate| Ok::<_, ()>(krate.items.len()));
+/// assert_eq!(output.unwrap(), 1);
+/// ```
+pub fn with_ast_snippet<T, E, F: Fn(&ParseSess, &Crate) -> Result<T, E>>(
+    source: &str,
+    callback: F,
+) -> SnippetOutput<T, E> {
+    let config = ParseConfig::from_source(SNIPPET_FILE_NAME, source).edition(Edition::Edition2021);
+    with_ast_snippet_config(config, callback)
+}
+
+/// Same as [`with_ast_snippet`], but the snippet and the options come from `config`. Its
+/// diagnostic output is replaced to capture the diagnostics.
+pub fn with_ast_snippet_config<T, E, F: Fn(&ParseSess, &Crate) -> Result<T, E>>(
+    config: ParseConfig,
+    callback: F,
+) -> SnippetOutput<T, E> {
+    let collector = DiagnosticCollector::new();
+    let config = config.diagnostic_output(DiagnosticOutput::Capture(collector.clone()));
+    SnippetOutput::new(with_ast_parser_config(&config, callback), collector)
+}
+
+/// Analyzes `source` as a library crate (in the 2021 edition) and calls `callback` with its
+/// `TyCtxt`, like [`with_tyctxt`](crate::with_tyctxt). The diagnostics are captured instead of
+/// being printed.
+///
+/// The analysis runs up to the default [`AnalysisLevel`](crate::AnalysisLevel), so the function
+/// bodies aren't type checked. Use [`with_tyctxt_snippet_config`] to run more passes.
+pub fn with_tyctxt_snippet<T: Send, E: Send, F: FnOnce(TyCtxt<'_>) -> Result<T, E> + Send>(
+    source: &str,
+    callback: F,
+) -> SnippetOutput<T, E> {
+    let config = AnalysisConfig::from_source(SNIPPET_FILE_NAME, source)
+        .edition(Edition::Edition2021)
+        .arg("--crate-type=lib");
+    with_tyctxt_snippet_config(config, callback)
+}
+
+/// Same as [`with_tyctxt_snippet`], but the snippet and the options come from `config`. Its
+/// diagnostic output is replaced to capture the diagnostics, and its outputs are written in a
+/// temporary directory unless it sets them (with `--emit`, `--out-dir` or `-o`).
+pub fn with_tyctxt_snippet_config<
+    T: Send,
+    E: Send,
+    F: FnOnce(TyCtxt<'_>) -> Result<T, E> + Send,
+>(
+    config: AnalysisConfig,
+    callback: F,
+) -> SnippetOutput<T, E> {
+    let collector = DiagnosticCollector::new();
+    let output_dir = OutputDir::new();
+    let config = output_dir
+        .apply(config)
+        .diagnostic_output(DiagnosticOutput::Capture(collector.clone()));
+    SnippetOutput::new(with_tyctxt_config(&config, callback), collector)
+}
+
+/// Temporary directory in which a compilation of the tests writes its outputs, so they don't end
+/// up in the current directory. It's removed when dropped.
+struct OutputDir(PathBuf);
+
+impl OutputDir {
+    fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "rustc-tools-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+        Self(std::env::temp_dir().join(name))
+    }
+
+    /// Only emits the metadata of the crate, in this directory. Nothing is changed if `config`
+    /// already sets the outputs.
+    fn apply(&self, config: AnalysisConfig) -> AnalysisConfig {
+        if config.has_arg(|arg| {
+            arg.starts_with("--emit") || arg.starts_with("--out-dir") || arg.starts_with("-o")
+        }) {
+            return config;
+        }
+        config
+            .arg("--emit=metadata")
+            .arg(format!("--out-dir={}", self.0.display()))
+    }
+}
+
+impl Drop for OutputDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CapturedSpan;
+
+    fn diagnostic(level: DiagnosticLevel, message: &str, line: usize) -> CapturedDiagnostic {
+        CapturedDiagnostic {
+            level,
+            message: message.to_owned(),
+            code: None,
+            spans: vec![CapturedSpan {
+                file_name: "foo.rs".to_owned(),
+                local_path: None,
+                byte_start: 0,
+                byte_end: 0,
+                line_start: line,
+                line_end: line,
+                column_start: 1,
+                column_end: 1,
+                is_primary: true,
+                label: None,
+            }],
+            children: Vec::new(),
+            suggestions: Vec::new(),
+            rendered: String::new(),
+        }
+    }
+
+    fn annotations(source: &str) -> Vec<(usize, &'static str, String)> {
+        parse_annotations(source)
+            .unwrap()
+            .into_iter()
+            .map(|report| (report.line, report.level, report.message))
+            .collect()
+    }
+
+    #[test]
+    fn annotation_lines() {
+        let source = "\
+fn foo() {} //~ ERROR same line
+//~^ WARN above
+//~| NOTE previous
+
+//~^^^ HELP three above
+//~|HELP
+";
+        assert_eq!(
+            annotations(source),
+            [
+                (1, "ERROR", "same line".to_owned()),
+                (1, "WARN", "above".to_owned()),
+                (1, "NOTE", "previous".to_owned()),
+                (2, "HELP", "three above".to_owned()),
+                (2, "HELP", String::new()),
+            ],
+        );
+        assert_eq!(annotations("fn foo() {} //~ WARNING x")[0].1, "WARN");
+    }
+
+    #[test]
+    fn annotation_errors() {
+        assert_eq!(
+            parse_annotations("//~| ERROR foo").unwrap_err(),
+            "line 1: `//~|` without a previous annotation",
+        );
+        assert_eq!(
+            parse_annotations("\n//~^^ ERROR foo").unwrap_err(),
+            "line 2: the annotation points before the file",
+        );
+        assert_eq!(
+            parse_annotations("fn foo() {} //~ WARM foo").unwrap_err(),
+            "line 1: unknown level `WARM` (expected `ERROR`, `WARN`, `NOTE` or `HELP`)",
+        );
+    }
+
+    #[test]
+    fn check() {
+        let path = Path::new("foo.rs");
+        let source = "fn foo() {} //~ WARN ugly\n//~| HELP remove\n";
+        let mut warning = diagnostic(DiagnosticLevel::Warning, "generics are ugly", 1);
+        warning.children.push(CapturedDiagnostic {
+            spans: Vec::new(),
+            ..diagnostic(DiagnosticLevel::Help, "remove them", 0)
+        });
+        assert!(check_annotations(path, source, &[warning.clone()]).is_empty());
+
+        let error = diagnostic(DiagnosticLevel::Error, "unexpected", 2);
+        let note = diagnostic(DiagnosticLevel::Note, "notes can be omitted", 2);
+        assert_eq!(
+            check_annotations(path, source, &[warning, error, note]),
+            ["line 2: unexpected ERROR `unexpected`"],
+        );
+        assert_eq!(
+            check_annotations(path, source, &[]),
+            [
+                "line 1: expected WARN `ugly` wasn't emitted",
+                "line 1: expected HELP `remove` wasn't emitted",
+            ],
+        );
+        // Without annotations, nothing is checked.
+        let error = diagnostic(DiagnosticLevel::Error, "unexpected", 1);
+        assert!(check_annotations(path, "fn foo() {}", &[error]).is_empty());
+    }
+
+    #[test]
+    fn diff() {
+        assert_eq!(line_diff("a\nb\nc\n", "a\nb\nc\n"), " a\n b\n c\n");
+        assert_eq!(
+            line_diff("a\nb\nc\n", "a\nx\nc\nd\n"),
+            " a\n+x\n-b\n c\n+d\n"
+        );
+        assert_eq!(line_diff("", "a\n"), "+a\n");
+        assert_eq!(line_diff("a\n", ""), "-a\n");
+    }
+
+    #[test]
+    fn normalize() {
+        let rendered = |rendered: &str| CapturedDiagnostic {
+            rendered: rendered.to_owned(),
+            ..diagnostic(DiagnosticLevel::Warning, "", 1)
+        };
+        let diagnostics = [
+            rendered(" --> /ui\\tests\\sub\\foo.rs:1:1\n"),
+            rendered("`\\n` in /ui\\tests/foo.rs: /other\\foo.rs\n"),
+        ];
+        assert_eq!(
+            normalize_output(Path::new("/ui\\tests"), &diagnostics),
+            " --> $DIR/sub/foo.rs:1:1\n`\\n` in $DIR/foo.rs: /other\\foo.rs\n",
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use rustc_lexer::{validate_raw_str, Cursor, LiteralKind, RawStrError, TokenKind};
+
+use std::ops::Range;
+
+/// Position in the source code. Both the line and the column start at 1, and the column is
+/// counted in characters (not in bytes).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Error found by the lexer in a token. The token is still returned by [`Tokens`], so the source
+/// code can always be rebuilt from the tokens.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenError {
+    /// A character which cannot start any token.
+    UnknownCharacter(char),
+    /// An identifier containing invalid characters (like emojis).
+    InvalidIdent,
+    /// An unknown prefix before a literal or a `#` (like `f"..."`). Note that rustc only reports
+    /// it since the 2021 edition.
+    UnknownPrefix,
+    /// A lifetime starting with a number (like `'1a`).
+    LifetimeStartingWithNumber,
+    /// A block comment which isn't closed.
+    UnterminatedBlockComment,
+    /// A char, byte or string literal which isn't closed.
+    UnterminatedLiteral,
+    /// A raw string literal which is invalid or isn't closed.
+    InvalidRawString(RawStrError),
+    /// An integer without digits (like `0x`).
+    EmptyInt,
+    /// A float without digits in its exponent (like `1e`).
+    EmptyExponent,
+}
+
+/// A token returned by [`Tokens`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// The source code of the token.
+    pub text: &'a str,
+    /// Byte range of the token in the source code.
+    pub range: Range<usize>,
+    /// Position of the first character of the token.
+    pub start: LineColumn,
+    /// Position right after the last character of the token.
+    pub end: LineColumn,
+    /// Error found by the lexer in this token, if any.
+    pub error: Option<TokenError>,
+}
+
+/// Iterator over the tokens of a source code, returned by [`tokens`]. It is lossless: the
+/// whitespaces and the comments are returned too, so concatenating the text of all the tokens
+/// gives back the source code.
+pub struct Tokens<'a> {
+    source: &'a str,
+    cursor: Cursor<'a>,
+    offset: usize,
+    position: LineColumn,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.cursor.advance_token();
+        if token.kind == TokenKind::Eof {
+            return None;
+        }
+        let range = self.offset..self.offset + token.len as usize;
+        let text = &self.source[range.clone()];
+        let start = self.position;
+        for c in text.chars() {
+            if c == '\n' {
+                self.position.line += 1;
+                self.position.column = 1;
+            } else {
+                self.position.column += 1;
+            }
+        }
+        self.offset = range.end;
+
+        Some(Token {
+            kind: token.kind,
+            text,
+            range,
+            start,
+            end: self.position,
+            error: token_error(token.kind, text),
+        })
+    }
+}
+
+/// Returns an iterator over the tokens of `source_code` with their position and their text. Unlike
+/// [`lexer`](crate::lexer), the lexer errors are reported (take a look at [`TokenError`]).
+pub fn tokens(source_code: &str) -> Tokens<'_> {
+    Tokens {
+        source: source_code,
+        cursor: Cursor::new(source_code),
+        offset: 0,
+        position: LineColumn { line: 1, column: 1 },
+    }
+}
+
+fn token_error(kind: TokenKind, text: &str) -> Option<TokenError> {
+    match kind {
+        TokenKind::Unknown => text.chars().next().map(TokenError::UnknownCharacter),
+        TokenKind::InvalidIdent => Some(TokenError::InvalidIdent),
+        TokenKind::UnknownPrefix => Some(TokenError::UnknownPrefix),
+        TokenKind::Lifetime {
+            starts_with_number: true,
+        } => Some(TokenError::LifetimeStartingWithNumber),
+        TokenKind::BlockComment {
+            terminated: false, ..
+        } => Some(TokenError::UnterminatedBlockComment),
+        TokenKind::Literal { kind, .. } => literal_error(kind, text),
+        _ => None,
+    }
+}
+
+fn literal_error(kind: LiteralKind, text: &str) -> Option<TokenError> {
+    match kind {
+        LiteralKind::Int {
+            empty_int: true, ..
+        } => Some(TokenError::EmptyInt),
+        LiteralKind::Float {
+            empty_exponent: true,
+            ..
+        } => Some(TokenError::EmptyExponent),
+        LiteralKind::Char { terminated: false }
+        | LiteralKind::Byte { terminated: false }
+        | LiteralKind::Str { terminated: false }
+        | LiteralKind::ByteStr { terminated: false }
+        | LiteralKind::CStr { terminated: false } => Some(TokenError::UnterminatedLiteral),
+        LiteralKind::RawStr { n_hashes: None } => raw_str_error(text, 1),
+        LiteralKind::RawByteStr { n_hashes: None } | LiteralKind::RawCStr { n_hashes: None } => {
+            raw_str_error(text, 2)
+        }
+        _ => None,
+    }
+}
+
+/// `prefix_len` is the length of the prefix before the `#` (`r`, `br` or `cr`).
+fn raw_str_error(text: &str, prefix_len: u32) -> Option<TokenError> {
+    validate_raw_str(text, prefix_len)
+        .err()
+        .map(TokenError::InvalidRawString)
+}
@@ -0,0 +1,56 @@
+#![feature(rustc_private)]
+
+extern crate rustc_ast;
+extern crate rustc_session;
+extern crate rustc_span;
+
+use rustc_ast::ast::Crate;
+use rustc_session::parse::ParseSess;
+use rustc_span::edition::Edition;
+use rustc_tools::{with_ast_parser_from_source, DiagnosticOutput, EditError, SourceEdits};
+
+use std::convert::Infallible;
+
+/// Renames the items of `source`.
+fn rename_items(source: &str) -> Vec<String> {
+    let callback = |sess: &ParseSess, krate: &Crate| {
+        let mut edits = SourceEdits::new();
+        for item in &krate.items {
+            let name = format!("{}_renamed", item.ident);
+            edits
+                .replace(sess.source_map(), item.ident.span, name)
+                .unwrap();
+        }
+        let first = krate.items[0].span;
+        assert!(matches!(
+            edits.replace(sess.source_map(), first, ""),
+            Err(EditError::Overlap(_)),
+        ));
+        Ok::<_, Infallible>(edits.apply().into_values().collect())
+    };
+    with_ast_parser_from_source(
+        "lib.rs",
+        source,
+        Edition::Edition2021,
+        DiagnosticOutput::default(),
+        callback,
+    )
+    .ok()
+    .unwrap()
+}
+
+#[test]
+fn edits() {
+    assert_eq!(
+        rename_items("fn foo() {}\nfn bar() {}\n"),
+        ["fn foo_renamed() {}\nfn bar_renamed() {}\n"],
+    );
+}
+
+#[test]
+fn edits_crlf() {
+    assert_eq!(
+        rename_items("\u{feff}fn foo() {}\r\nfn bar() {}\r\n"),
+        ["\u{feff}fn foo_renamed() {}\r\nfn bar_renamed() {}\r\n"],
+    );
+}
@@ -0,0 +1,178 @@
+#![feature(rustc_private)]
+
+extern crate rustc_ast;
+extern crate rustc_errors;
+extern crate rustc_lint;
+extern crate rustc_session;
+
+use rustc_ast::ast::{Item, ItemKind};
+use rustc_errors::Applicability;
+use rustc_lint::{EarlyContext, EarlyLintPass, LintContext};
+use rustc_session::{declare_lint_pass, declare_tool_lint};
+use rustc_tools::{
+    with_lints_fix, AnalysisConfig, CapturedDiagnostic, CapturedSpan, CapturedSuggestion,
+    CapturedSuggestionPart, DiagnosticOutput, EditError, SourceEdits,
+};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+declare_tool_lint! {
+    pub fix::RENAME_FUNCTIONS,
+    Warn,
+    "suggests to rename the functions",
+    report_in_external_macro: false
+}
+declare_tool_lint! {
+    pub fix::REMOVE_FUNCTIONS,
+    Warn,
+    "suggests to remove the functions",
+    report_in_external_macro: false
+}
+declare_lint_pass!(Functions => [RENAME_FUNCTIONS, REMOVE_FUNCTIONS]);
+
+impl EarlyLintPass for Functions {
+    fn check_item(&mut self, cx: &EarlyContext<'_>, item: &Item) {
+        if !matches!(item.kind, ItemKind::Fn(_)) {
+            return;
+        }
+        cx.struct_span_lint(RENAME_FUNCTIONS, item.ident.span, "bad name", |diag| {
+            diag.span_suggestion(
+                item.ident.span,
+                "rename it",
+                format!("{}_renamed", item.ident),
+                Applicability::MachineApplicable,
+            )
+        });
+        // Overlaps the previous suggestion, so it is rejected.
+        cx.struct_span_lint(REMOVE_FUNCTIONS, item.span, "useless function", |diag| {
+            diag.span_suggestion(item.span, "remove it", "", Applicability::MachineApplicable)
+        });
+    }
+}
+
+/// Writes `source` in a new file and returns its path.
+fn write_crate(name: &str, source: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lib.rs");
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn messages(diagnostics: &[CapturedDiagnostic]) -> Vec<&str> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect()
+}
+
+fn config(path: &Path) -> AnalysisConfig {
+    AnalysisConfig::new(path)
+        .arg("--crate-type=lib")
+        .arg("--emit=metadata")
+        .arg(format!("--out-dir={}", path.parent().unwrap().display()))
+        .diagnostic_output(DiagnosticOutput::Capture(Default::default()))
+}
+
+#[test]
+fn lints_fix() {
+    let path = write_crate("fix", "pub fn foo() {}\npub fn bar() {}\n");
+    let fixes = with_lints_fix(
+        &config(&path),
+        &[Applicability::MachineApplicable],
+        |store| {
+            store.register_lints(&[&RENAME_FUNCTIONS, &REMOVE_FUNCTIONS]);
+            store.register_early_pass(|| Box::new(Functions));
+        },
+    )
+    .unwrap();
+
+    assert_eq!(messages(&fixes.fixed), ["bad name", "bad name"]);
+    assert_eq!(
+        messages(&fixes.unfixed),
+        ["useless function", "useless function", "4 warnings emitted"],
+    );
+    let span = &fixes.fixed[0].suggestions[0].parts[0].span;
+    assert_eq!(span.local_path.as_ref(), Some(&path));
+
+    let path_name = path.display();
+    assert_eq!(
+        fixes.edits.diff(),
+        format!(
+            "--- {path_name}\n+++ {path_name}\n@@ -1,2 +1,2 @@\n-pub fn foo() {{}}\n+pub fn foo_renamed() {{}}\n\
+             -pub fn bar() {{}}\n+pub fn bar_renamed() {{}}\n"
+        ),
+    );
+    fixes.edits.write().unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "pub fn foo_renamed() {}\npub fn bar_renamed() {}\n",
+    );
+}
+
+#[test]
+fn lints_fix_applicability() {
+    let source = "pub fn foo() {}\n";
+    let path = write_crate("fix_applicability", source);
+    let fixes = with_lints_fix(&config(&path), &[Applicability::MaybeIncorrect], |store| {
+        store.register_lints(&[&RENAME_FUNCTIONS, &REMOVE_FUNCTIONS]);
+        store.register_early_pass(|| Box::new(Functions));
+    })
+    .unwrap();
+
+    assert!(fixes.fixed.is_empty());
+    assert_eq!(
+        messages(&fixes.unfixed),
+        ["bad name", "useless function", "2 warnings emitted"],
+    );
+    assert!(fixes.edits.is_empty());
+    assert_eq!(fixes.edits.diff(), "");
+}
+
+fn suggestion(path: &Path, parts: &[(u32, u32, &str)]) -> CapturedSuggestion {
+    let parts = parts
+        .iter()
+        .map(
+            |&(byte_start, byte_end, replacement)| CapturedSuggestionPart {
+                span: CapturedSpan {
+                    // Relative to the working directory of the compiler, not to the current directory.
+                    file_name: "lib.rs".to_owned(),
+                    local_path: Some(path.to_owned()),
+                    byte_start,
+                    byte_end,
+                    line_start: 1,
+                    line_end: 1,
+                    column_start: byte_start as usize + 1,
+                    column_end: byte_end as usize + 1,
+                    is_primary: true,
+                    label: None,
+                },
+                replacement: replacement.to_owned(),
+            },
+        )
+        .collect();
+    CapturedSuggestion {
+        message: String::new(),
+        applicability: Applicability::MachineApplicable,
+        parts,
+    }
+}
+
+#[test]
+fn add_suggestion() {
+    let path = write_crate("add_suggestion", "fn foo(a: u8) {}\n");
+    let mut edits = SourceEdits::new();
+    edits
+        .add_suggestion(&suggestion(&path, &[(3, 6, "bar"), (7, 8, "b")]))
+        .unwrap();
+    // None of the parts are added if one of them overlaps.
+    assert_eq!(
+        edits.add_suggestion(&suggestion(&path, &[(10, 12, "u16"), (0, 16, "")])),
+        Err(EditError::Overlap(3..6)),
+    );
+    assert_eq!(
+        edits.apply().into_values().collect::<Vec<_>>(),
+        ["fn bar(b: u8) {}\n"],
+    );
+}
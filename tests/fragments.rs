@@ -0,0 +1,29 @@
+#![feature(rustc_private)]
+
+extern crate rustc_ast;
+extern crate rustc_span;
+
+use rustc_ast::ast::ExprKind;
+use rustc_span::edition::Edition;
+use rustc_tools::{with_expr_parser, DiagnosticCollector, DiagnosticOutput, Error};
+
+use std::convert::Infallible;
+
+#[test]
+fn expr_fragment() {
+    let is_call = with_expr_parser(
+        "foo(1 + 2)",
+        Edition::Edition2021,
+        DiagnosticOutput::default(),
+        |_, expr| Ok::<_, Infallible>(matches!(expr.kind, ExprKind::Call(..))),
+    );
+    assert!(is_call.ok().unwrap());
+
+    let result = with_expr_parser(
+        "1 +",
+        Edition::Edition2021,
+        DiagnosticOutput::Capture(DiagnosticCollector::new()),
+        |_, _| Ok::<_, Infallible>(()),
+    );
+    assert!(matches!(result, Err(Error::Parser(errors)) if !errors.is_empty()));
+}
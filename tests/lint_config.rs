@@ -0,0 +1,108 @@
+#![feature(rustc_private)]
+
+extern crate rustc_ast;
+extern crate rustc_lint;
+extern crate rustc_session;
+
+use rustc_ast::ast::{Item, ItemKind};
+use rustc_lint::{EarlyContext, EarlyLintPass, LintContext};
+use rustc_session::{declare_lint_pass, declare_tool_lint};
+use rustc_tools::{
+    with_lints_config, AnalysisConfig, CapturedDiagnostic, DiagnosticCollector, DiagnosticLevel,
+    DiagnosticOutput, Error, LintConfig, LintConfigExt,
+};
+
+use std::fs;
+use std::path::PathBuf;
+
+declare_tool_lint! {
+    pub mytool::FUNCTIONS,
+    Warn,
+    "reports the functions",
+    report_in_external_macro: false
+}
+declare_lint_pass!(Functions => [FUNCTIONS]);
+
+impl EarlyLintPass for Functions {
+    fn check_item(&mut self, cx: &EarlyContext<'_>, item: &Item) {
+        if !matches!(item.kind, ItemKind::Fn(_)) {
+            return;
+        }
+        let name = cx
+            .lint_config()
+            .option::<String>(FUNCTIONS, "name")
+            .unwrap_or_else(|| "function".to_owned());
+        cx.struct_span_lint(FUNCTIONS, item.ident.span, name, |diag| diag);
+    }
+}
+
+/// Runs the `Functions` lint on a crate with a single function and returns whether the
+/// compilation failed and the diagnostics of the lint.
+fn run(
+    name: &str,
+    lint_config: Option<LintConfig>,
+    args: &[&str],
+) -> (bool, Vec<CapturedDiagnostic>) {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lib.rs");
+    fs::write(&path, "pub fn foo() {}\n").unwrap();
+
+    let collector = DiagnosticCollector::new();
+    let mut config = AnalysisConfig::new(&path)
+        .arg("--crate-type=lib")
+        .arg("--emit=metadata")
+        .arg(format!("--out-dir={}", dir.display()))
+        .diagnostic_output(DiagnosticOutput::Capture(collector.clone()));
+    for arg in args {
+        config = config.arg(*arg);
+    }
+    if let Some(lint_config) = lint_config {
+        config = config.lint_config(lint_config);
+    }
+    let result = with_lints_config(&config, |store| {
+        store.register_lints(&[&FUNCTIONS]);
+        store.register_early_pass(|| Box::new(Functions));
+    });
+    let failed = match result {
+        Ok(()) => false,
+        Err(Error::Compilation(_)) => true,
+        Err(error) => panic!("unexpected error: {}", error),
+    };
+    let diagnostics = collector
+        .take()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code.is_some())
+        .collect();
+    (failed, diagnostics)
+}
+
+#[test]
+fn configured_level() {
+    let lint_config =
+        LintConfig::parse("[\"mytool::functions\"]\nlevel = \"deny\"\nname = \"fn\"\n").unwrap();
+    let (failed, diagnostics) = run("lint_config", Some(lint_config), &[]);
+    assert!(failed);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, DiagnosticLevel::Error);
+    assert_eq!(diagnostics[0].message, "fn");
+
+    // The configuration of the previous run isn't used anymore.
+    let (failed, diagnostics) = run("lint_config_default", None, &[]);
+    assert!(!failed);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].level, DiagnosticLevel::Warning);
+    assert_eq!(diagnostics[0].message, "function");
+}
+
+#[test]
+fn command_line_wins() {
+    let lint_config = LintConfig::parse("[\"mytool::functions\"]\nlevel = \"deny\"\n").unwrap();
+    let (failed, diagnostics) = run(
+        "lint_config_allow",
+        Some(lint_config),
+        &["-Amytool::functions"],
+    );
+    assert!(!failed);
+    assert!(diagnostics.is_empty());
+}
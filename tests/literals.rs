@@ -0,0 +1,104 @@
+#![feature(rustc_private)]
+
+extern crate rustc_ast;
+extern crate rustc_lexer;
+
+use rustc_ast::ast::{FloatTy, LitFloatType, LitIntType, UintTy};
+use rustc_lexer::unescape::EscapeError;
+use rustc_tools::{tokens, unescape_literal, Literal, LiteralError, LiteralErrorKind};
+
+use std::ffi::CString;
+
+/// Returns the value of the only literal of `source`.
+fn literal(source: &str) -> Literal {
+    let token = tokens(source).next().unwrap();
+    unescape_literal(&token).unwrap().unwrap()
+}
+
+/// Returns the errors of the first literal of `source`.
+fn errors(source: &str) -> Vec<LiteralError> {
+    tokens(source)
+        .find_map(|token| unescape_literal(&token))
+        .unwrap()
+        .unwrap_err()
+}
+
+fn escape_error(error: EscapeError, range: std::ops::Range<usize>) -> LiteralError {
+    LiteralError {
+        kind: LiteralErrorKind::Escape(error),
+        range,
+    }
+}
+
+#[test]
+fn literals() {
+    assert_eq!(literal("\"a\\n\""), Literal::Str("a\n".to_owned()));
+    assert_eq!(
+        literal("1u8"),
+        Literal::Int(1, LitIntType::Unsigned(UintTy::U8))
+    );
+}
+
+#[test]
+fn strings() {
+    assert_eq!(literal("'é'"), Literal::Char('é'));
+    assert_eq!(literal("b'\\xff'"), Literal::Byte(0xff));
+    assert_eq!(literal("r#\"a\\n\"#"), Literal::Str("a\\n".to_owned()));
+    assert_eq!(literal("b\"a\\x80\""), Literal::ByteStr(vec![b'a', 0x80]));
+    assert_eq!(literal("br\"\\n\""), Literal::ByteStr(b"\\n".to_vec()));
+    assert_eq!(
+        literal("c\"a\\u{e9}\""),
+        Literal::CStr(CString::new("aé").unwrap())
+    );
+    assert_eq!(
+        literal("cr\"\\0\""),
+        Literal::CStr(CString::new("\\0").unwrap())
+    );
+}
+
+#[test]
+fn floats() {
+    assert_eq!(
+        literal("1.5e3"),
+        Literal::Float(1500., LitFloatType::Unsuffixed)
+    );
+    assert_eq!(
+        literal("2.5f32"),
+        Literal::Float(2.5, LitFloatType::Suffixed(FloatTy::F32))
+    );
+    assert_eq!(
+        literal("1_0.0_1"),
+        Literal::Float(10.01, LitFloatType::Unsuffixed)
+    );
+}
+
+#[test]
+fn bad_escapes() {
+    // The ranges are the ones of the escapes in the whole source code.
+    assert_eq!(
+        errors("let s = \"\\q\";"),
+        [escape_error(EscapeError::InvalidEscape, 9..11)],
+    );
+    assert_eq!(
+        errors("'\\u{110000}'"),
+        [escape_error(EscapeError::OutOfRangeUnicodeEscape, 1..11)],
+    );
+    assert_eq!(
+        errors("\"a\\x\""),
+        [escape_error(EscapeError::TooShortHexEscape, 2..4)],
+    );
+    assert_eq!(
+        errors("\"\\q \\x4\""),
+        [
+            escape_error(EscapeError::InvalidEscape, 1..3),
+            escape_error(EscapeError::TooShortHexEscape, 4..7),
+        ],
+    );
+    assert_eq!(
+        errors("c\"a\\0\""),
+        [LiteralError {
+            kind: LiteralErrorKind::NulInCStr,
+            range: 3..5,
+        }],
+    );
+}
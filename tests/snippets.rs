@@ -0,0 +1,51 @@
+#![feature(rustc_private)]
+
+extern crate rustc_hir;
+
+use rustc_hir::def::DefKind;
+use rustc_tools::testing::{with_ast_snippet, with_tyctxt_snippet, SNIPPET_FILE_NAME};
+use rustc_tools::{item_to_string, DiagnosticLevel, Error};
+
+use std::convert::Infallible;
+
+#[test]
+fn ast_snippet() {
+    let items = with_ast_snippet("fn foo() {}\nstruct Bar;", |_, krate| {
+        Ok::<_, Infallible>(
+            krate
+                .items
+                .iter()
+                .map(|item| item_to_string(item))
+                .collect::<Vec<_>>(),
+        )
+    })
+    .unwrap();
+    assert_eq!(items, ["fn foo() {}", "struct Bar;"]);
+}
+
+#[test]
+fn ast_snippet_parse_error() {
+    let output = with_ast_snippet("fn foo( {}", |_, _| Ok::<_, Infallible>(()));
+    assert!(matches!(output.result, Err(Error::Parser(_))));
+    let error = output
+        .diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.level == DiagnosticLevel::Error)
+        .unwrap();
+    assert_eq!(error.primary_span().unwrap().file_name, SNIPPET_FILE_NAME);
+    assert!(output.rendered_diagnostics().contains("--> snippet.rs:1:"));
+}
+
+#[test]
+fn tyctxt_snippet() {
+    let output = with_tyctxt_snippet("pub struct Foo;\npub fn foo() -> Foo { Foo }", |tcx| {
+        // The prelude imports of `std` are items too.
+        let items = tcx
+            .hir()
+            .items()
+            .filter(|item| matches!(tcx.def_kind(item.owner_id), DefKind::Struct | DefKind::Fn));
+        Ok::<_, Infallible>(items.count())
+    });
+    assert!(output.diagnostics.is_empty());
+    assert_eq!(output.unwrap(), 2);
+}
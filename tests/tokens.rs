@@ -0,0 +1,99 @@
+#![feature(rustc_private)]
+
+extern crate rustc_lexer;
+
+use rustc_lexer::TokenKind;
+use rustc_tools::{tokens, LineColumn, TokenError};
+
+#[test]
+fn lossless_tokens() {
+    let source = "let s = \"a\\n\"; // comment\nlet n = 1u8;";
+    let tokens = tokens(source).collect::<Vec<_>>();
+    assert_eq!(
+        tokens.iter().map(|token| token.text).collect::<String>(),
+        source
+    );
+    assert!(tokens.iter().all(|token| token.error.is_none()));
+
+    let comment = tokens
+        .iter()
+        .find(|token| token.text == "// comment")
+        .unwrap();
+    assert_eq!(comment.kind, TokenKind::LineComment { doc_style: None });
+    assert_eq!(
+        comment.start,
+        LineColumn {
+            line: 1,
+            column: 16
+        }
+    );
+    assert_eq!(
+        comment.end,
+        LineColumn {
+            line: 1,
+            column: 26
+        }
+    );
+    let n = tokens.iter().find(|token| token.text == "n").unwrap();
+    assert_eq!(n.start, LineColumn { line: 2, column: 5 });
+}
+
+/// Returns the text and the error of the tokens of `source`.
+fn errors(source: &str) -> Vec<(&str, Option<TokenError>)> {
+    tokens(source)
+        .map(|token| (token.text, token.error))
+        .collect()
+}
+
+#[test]
+fn error_tokens() {
+    assert_eq!(
+        errors("\"abc"),
+        [("\"abc", Some(TokenError::UnterminatedLiteral))],
+    );
+    assert_eq!(
+        errors("'\\'"),
+        [("'\\'", Some(TokenError::UnterminatedLiteral))],
+    );
+    assert_eq!(
+        errors("a € b"),
+        [
+            ("a", None),
+            (" ", None),
+            ("€", Some(TokenError::UnknownCharacter('€'))),
+            (" ", None),
+            ("b", None),
+        ],
+    );
+    assert_eq!(
+        errors("/* a /* b */"),
+        [("/* a /* b */", Some(TokenError::UnterminatedBlockComment))],
+    );
+    assert_eq!(errors("0x"), [("0x", Some(TokenError::EmptyInt))]);
+}
+
+#[test]
+fn spans_round_trip() {
+    let source = "fn é() {\n    \"ü\\\"\" /* ☃\n */ 'a' €\r\n}\n";
+    let lines = source.split('\n').collect::<Vec<_>>();
+    for token in tokens(source) {
+        assert_eq!(&source[token.range.clone()], token.text);
+        // Converts the positions (in characters) back into byte offsets.
+        for (position, offset) in [
+            (token.start, token.range.start),
+            (token.end, token.range.end),
+        ] {
+            let line_start = lines[..position.line - 1]
+                .iter()
+                .map(|line| line.len() + 1)
+                .sum::<usize>();
+            let column = lines[position.line - 1]
+                .char_indices()
+                .map(|(index, _)| index)
+                .chain([lines[position.line - 1].len()])
+                .nth(position.column - 1)
+                .unwrap();
+            assert_eq!(line_start + column, offset, "{:?}", token);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#![feature(rustc_private)]
+
+extern crate rustc_ast;
+extern crate rustc_lint;
+extern crate rustc_session;
+
+use rustc_lint::{EarlyContext, EarlyLintPass, LintContext};
+use rustc_session::{declare_lint_pass, declare_tool_lint};
+use rustc_tools::testing::UiTests;
+
+declare_tool_lint! {
+    pub ui::WARN_GENERICS,
+    Warn,
+    "warns if any item has generics",
+    report_in_external_macro: false
+}
+declare_lint_pass!(WarnGenerics => [WARN_GENERICS]);
+
+impl EarlyLintPass for WarnGenerics {
+    fn check_item(&mut self, cx: &EarlyContext<'_>, item: &rustc_ast::Item) {
+        if let Some(generics) = item.kind.generics() {
+            if generics.params.is_empty() {
+                return;
+            }
+            cx.struct_span_lint(WARN_GENERICS, generics.span, "generics are ugly", |diag| {
+                diag.help("remove the generic parameters")
+            });
+        }
+    }
+}
+
+// Run with `BLESS=1 cargo test --test ui` to update the `.stderr` files.
+#[test]
+fn ui() {
+    UiTests::new("tests/ui")
+        .bless(std::env::var_os("BLESS").is_some())
+        .run(|store| {
+            store.register_lints(&[&WARN_GENERICS]);
+            store.register_early_pass(|| Box::new(WarnGenerics));
+        })
+        .assert_ok();
+}
@@ -0,0 +1,11 @@
+pub fn no_generics() {}
+
+pub fn one<T>(_: T) {} //~ WARN generics are ugly
+//~| HELP remove the generic parameters
+
+pub struct Two<A, B>(A, B);
+//~^ WARN generics are ugly
+//~| HELP remove the generic parameters
+
+#[allow(ui::warn_generics)]
+pub fn allowed<T>(_: T) {}